@@ -0,0 +1,8327 @@
+//! Sidecar runtime (web, backend, mongo) behind the desktop shell. `RuntimeController` owns
+//! the lifecycle and has no Tauri dependency; `main.rs` only adapts it to Tauri commands.
+
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::hash::{BuildHasher, Hasher};
+use std::env;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RuntimeMode {
+    LocalFullstack,
+    RemoteSlim,
+}
+
+impl RuntimeMode {
+    fn from_raw(value: &str) -> Self {
+        match value.trim() {
+            "desktop_remote_slim" | "remote_slim" => Self::RemoteSlim,
+            _ => Self::LocalFullstack,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::LocalFullstack => "local_fullstack",
+            Self::RemoteSlim => "remote_slim",
+        }
+    }
+
+    fn as_backend_runtime_mode(self) -> &'static str {
+        match self {
+            Self::LocalFullstack => "desktop_local_fullstack",
+            Self::RemoteSlim => "desktop_remote_slim",
+        }
+    }
+}
+
+// What to do when mongod refuses to start over a stale mongod.lock; off unless configured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MongoLockRecovery {
+    Off,
+    RemoveLock,
+    Repair,
+}
+
+impl MongoLockRecovery {
+    fn from_raw(value: &str) -> Self {
+        match value.trim() {
+            "remove_lock" => Self::RemoveLock,
+            "repair" => Self::Repair,
+            _ => Self::Off,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RestartPolicy {
+    Always,
+    OnFailure,
+    Never,
+}
+
+impl RestartPolicy {
+    fn from_raw(value: &str) -> Self {
+        match value.trim() {
+            "on_failure" | "on-failure" => Self::OnFailure,
+            "never" => Self::Never,
+            _ => Self::Always,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct RestartPolicies {
+    web: RestartPolicy,
+    backend: RestartPolicy,
+    mongo: RestartPolicy,
+}
+
+impl RestartPolicies {
+    fn from_profile(raw: Option<&ServiceRestartPolicies>) -> Self {
+        let parse = |value: Option<&String>| {
+            value
+                .map(|text| RestartPolicy::from_raw(text))
+                .unwrap_or(RestartPolicy::Always)
+        };
+        Self {
+            web: parse(raw.and_then(|p| p.web.as_ref())),
+            backend: parse(raw.and_then(|p| p.backend.as_ref())),
+            mongo: parse(raw.and_then(|p| p.mongo.as_ref())),
+        }
+    }
+
+    fn for_service(&self, service: &str) -> RestartPolicy {
+        match service {
+            "web" => self.web,
+            "backend" => self.backend,
+            "mongo" => self.mongo,
+            _ if backend_replica_index(service).is_some() => self.backend,
+            _ => RestartPolicy::Always,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct ServiceRestartPolicies {
+    web: Option<String>,
+    backend: Option<String>,
+    mongo: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct MongoAuthProfile {
+    username: Option<String>,
+    password: Option<String>,
+    mongosh_bin: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct BackendDebugProfile {
+    enabled: Option<bool>,
+    port: Option<u16>,
+    wait_for_client: Option<bool>,
+    ready_timeout_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct BackendDebugConfig {
+    port: u16,
+    wait_for_client: bool,
+    ready_timeout: Duration,
+}
+
+#[derive(Debug, Clone)]
+struct MongoAuthConfig {
+    username: String,
+    password: String,
+    mongosh_bin: String,
+}
+
+#[derive(Debug, Clone)]
+struct OnReadyHook {
+    program: String,
+    args: Vec<String>,
+    timeout: Duration,
+    after_recovery: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct RuntimeTaskProfile {
+    program: Option<String>,
+    args: Option<Vec<String>>,
+    timeout_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct LocalPorts {
+    web: Option<u16>,
+    backend: Option<u16>,
+    mongo: Option<u16>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct RuntimeProfile {
+    mode: Option<String>,
+    backend_url: Option<String>,
+    local_ports: Option<LocalPorts>,
+    data_dir: Option<String>,
+    mongo_dbpath: Option<String>,
+    env: Option<BTreeMap<String, String>>,
+    backend_env: Option<BTreeMap<String, String>>,
+    web_env: Option<BTreeMap<String, String>>,
+    mongo_env: Option<BTreeMap<String, String>>,
+    diagnostics_path: Option<String>,
+    graceful_restart: Option<bool>,
+    min_node_version: Option<String>,
+    min_python_version: Option<String>,
+    enforce_min_versions: Option<bool>,
+    watch_paths: Option<Vec<String>>,
+    restart_policy: Option<ServiceRestartPolicies>,
+    session_id: Option<String>,
+    instance_label: Option<String>,
+    min_event_level: Option<String>,
+    log_to_stderr: Option<bool>,
+    persist_diagnostics: Option<bool>,
+    mongo_auth: Option<MongoAuthProfile>,
+    health_check_interval_ms: Option<u64>,
+    max_unhealthy_before_restart: Option<u32>,
+    autorestart_cooldown_ms: Option<u64>,
+    web_stop_grace_ms: Option<u64>,
+    backend_stop_grace_ms: Option<u64>,
+    mongo_stop_grace_ms: Option<u64>,
+    stop_total_timeout_ms: Option<u64>,
+    readiness_consecutive_ok: Option<u32>,
+    max_total_log_bytes: Option<u64>,
+    local_backend_scheme: Option<String>,
+    tls_insecure: Option<bool>,
+    web_disable_tls_verification: Option<bool>,
+    backend_log_path: Option<String>,
+    backend_drain_path: Option<String>,
+    backend_config_path: Option<String>,
+    tasks: Option<BTreeMap<String, RuntimeTaskProfile>>,
+    on_ready_command: Option<String>,
+    on_ready_args: Option<Vec<String>>,
+    on_ready_timeout_ms: Option<u64>,
+    on_ready_after_recovery: Option<bool>,
+    start_max_attempts: Option<u32>,
+    partial_start_ok: Option<bool>,
+    adopt_existing_mongo: Option<bool>,
+    stop_adopted: Option<bool>,
+    mongo_recover_lock: Option<String>,
+    mongo_uri_env_name: Option<String>,
+    mongo_default_db: Option<String>,
+    observe_only: Option<bool>,
+    backend_url_env_name: Option<String>,
+    web_profile_arg: Option<String>,
+    backend_debug: Option<BackendDebugProfile>,
+    backend_replicas: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DesktopRuntimeStartRequest {
+    pub mode: Option<String>,
+    pub profile_path: Option<String>,
+    pub web_dev: Option<bool>,
+    pub mongo_bin: Option<String>,
+    pub python_bin: Option<String>,
+    pub dry_run: Option<bool>,
+    pub session_id: Option<String>,
+    pub correlation_id: Option<String>,
+    pub instance_label: Option<String>,
+    pub observe_only: Option<bool>,
+    pub backend_debug: Option<bool>,
+    pub workspace_root: Option<String>,
+    // Inline profile for `trial_start`, used instead of reading `profile_path`.
+    #[serde(skip)]
+    trial_profile: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone)]
+struct RuntimeLaunchConfig {
+    mode: RuntimeMode,
+    web_port: u16,
+    backend_port: u16,
+    mongo_port: u16,
+    backend_url: String,
+    desktop_session_id: String,
+    runtime_profile_path: Option<String>,
+    web_dev: bool,
+    mongo_bin: Option<String>,
+    python_bin: String,
+    web_dir: PathBuf,
+    backend_dir: PathBuf,
+    data_dir: Option<String>,
+    mongo_dbpath: Option<PathBuf>,
+    env: BTreeMap<String, String>,
+    backend_env: BTreeMap<String, String>,
+    web_env: BTreeMap<String, String>,
+    mongo_env: BTreeMap<String, String>,
+    graceful_restart: bool,
+    log_dir: PathBuf,
+    watch_paths: Vec<PathBuf>,
+    restart_policies: RestartPolicies,
+    mongo_auth: Option<MongoAuthConfig>,
+    health_check_interval_ms: Option<u64>,
+    max_unhealthy_before_restart: u32,
+    autorestart_cooldown_ms: Option<u64>,
+    web_stop_grace_ms: Option<u64>,
+    backend_stop_grace_ms: Option<u64>,
+    mongo_stop_grace_ms: Option<u64>,
+    stop_total_timeout_ms: u64,
+    readiness_consecutive_ok: u32,
+    max_total_log_bytes: Option<u64>,
+    local_backend_scheme: &'static str,
+    web_disable_tls_verification: bool,
+    adopt_existing_mongo: bool,
+    stop_adopted: bool,
+    mongo_lock_recovery: MongoLockRecovery,
+    // Env var the backend gets the Mongo URI in, and the database put in its path.
+    mongo_uri_env_name: String,
+    mongo_default_db: Option<String>,
+    backend_log_path: String,
+    backend_drain_path: Option<String>,
+    backend_config_path: Option<String>,
+    backend_url_env_names: Vec<String>,
+    // Flag the standalone web launcher takes the profile path with; None skips passing it.
+    web_profile_arg: Option<String>,
+    // Backend launched under debugpy; None for a normal launch.
+    backend_debug: Option<BackendDebugConfig>,
+    // Ports of the extra backend replicas (services `backend-1`, ...) on the ports after
+    // backend_port; empty for a single backend.
+    backend_replica_ports: Vec<u16>,
+    tasks: BTreeMap<String, RuntimeTaskProfile>,
+    on_ready: Option<OnReadyHook>,
+    correlation_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DesktopRuntimeStatus {
+    pub running: bool,
+    pub mode: String,
+    pub web_pid: Option<u32>,
+    pub backend_pid: Option<u32>,
+    pub mongo_pid: Option<u32>,
+    // None when the service is not up; false when it was adopted rather than spawned.
+    pub web_owned: Option<bool>,
+    pub backend_owned: Option<bool>,
+    pub mongo_owned: Option<bool>,
+    // Where to attach a debugger when the backend runs under debugpy.
+    pub backend_debug_port: Option<u16>,
+    // Every backend instance with `backend_replicas` > 1, the first one included; else empty.
+    pub backend_replicas: Vec<DesktopRuntimeBackendReplica>,
+    pub started_at_ms: Option<u64>,
+    pub last_error: Option<String>,
+    pub web_port: u16,
+    pub backend_port: u16,
+    pub mongo_port: u16,
+    pub backend_url: String,
+    pub auto_restart: bool,
+    pub restart_count: u32,
+    pub unhealthy_restart_count: u32,
+    pub last_restart_ms: Option<u64>,
+    // Set while auto-restart is held off after repeated failures and a sidecar is down.
+    pub restart_backoff_until_ms: Option<u64>,
+    pub diagnostics_path: Option<String>,
+    pub planned_commands: Option<Vec<DesktopRuntimeCommandPreview>>,
+    pub port_adjusted: bool,
+    pub workspace_root: Option<String>,
+    pub web_dir: Option<String>,
+    pub backend_dir: Option<String>,
+    pub desktop_session_id: Option<String>,
+    pub correlation_id: Option<String>,
+    // Tells shells apart in a multi-instance UI; the profile's or request's, else the session id.
+    pub instance_label: Option<String>,
+    pub backend_scheme: String,
+    pub mongo_dbpath: Option<String>,
+    pub mongo_fresh: bool,
+    pub launch_warnings: Vec<String>,
+    pub start_phase: Option<String>,
+    pub start_progress: f32,
+    pub observe_only: bool,
+    // Set by `suspend_watchdog`: exits are still recorded, but nothing is restarted.
+    pub watchdog_suspended: bool,
+    // A watchdog recovery or a single-service restart is respawning these right now.
+    pub restart_in_progress: bool,
+    pub restarting_services: Vec<String>,
+    // The profile file was edited since the start; `config_drift_fields` lists what changed.
+    pub config_drift: bool,
+    pub config_drift_fields: Vec<String>,
+    // What the last stop (manual or after a failed start) did to each sidecar.
+    pub last_stop: Vec<DesktopRuntimeServiceStop>,
+    pub observed: Vec<DesktopRuntimeProbe>,
+    // Last successful backend health probe body as the backend reported it (JSON, or the raw text).
+    pub backend_health: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DesktopRuntimeStartProgress {
+    pub phase: String,
+    pub progress: f32,
+    pub ts_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DesktopRuntimeCommandPreview {
+    pub service: String,
+    pub program: String,
+    pub args: Vec<String>,
+    pub cwd: Option<String>,
+    pub env: BTreeMap<String, Option<String>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DesktopRuntimeWebEnv {
+    pub mode: String,
+    // "running": what the running web sidecar was given; "profile": what a start would set now.
+    pub source: String,
+    pub env: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct RuntimeSessionRecord {
+    updated_at_ms: u64,
+    desktop_session_id: Option<String>,
+    instance_label: Option<String>,
+    web_pid: Option<u32>,
+    backend_pid: Option<u32>,
+    mongo_pid: Option<u32>,
+    restart_count: u32,
+    last_restart_ms: Option<u64>,
+    auto_restart: bool,
+    auto_restart_disabled_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DesktopRuntimeProfileIssue {
+    pub pointer: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DesktopRuntimePaths {
+    // Where files are (for a started runtime) or where the next start would put them.
+    pub started: bool,
+    pub data_dir: Option<String>,
+    pub runtime_dir: String,
+    pub diagnostics_path: String,
+    pub log_dir: String,
+    pub session_path: String,
+    // None when mongo is not spawned (remote_slim) or mongod picks its own default dbpath.
+    pub mongo_dbpath: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DesktopRuntimeProfileValidation {
+    pub path: String,
+    pub valid: bool,
+    pub errors: Vec<DesktopRuntimeProfileIssue>,
+    // Schema keywords the validator could not check; they do not make the profile invalid.
+    pub warnings: Vec<DesktopRuntimeProfileIssue>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DesktopRuntimeProbe {
+    pub target: String,
+    pub ok: bool,
+    pub latency_ms: u64,
+    pub message: String,
+    // "mongo", "http" or "tcp_tls" (an https target, only checked for TCP reachability).
+    pub kind: String,
+    // host:port probed, or the socket path for a backend on a Unix socket.
+    pub address: Option<String>,
+    // HTTP probes that got a response, passing or not.
+    pub http_status: Option<u16>,
+    // Why a failed probe failed, without the target prefix `message` has.
+    pub error: Option<String>,
+    #[serde(skip)]
+    body: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DesktopRuntimeBackendReplica {
+    pub service: String,
+    pub port: u16,
+    pub pid: Option<u32>,
+    // None until a health check (health_check_interval_ms) has run; false while failing.
+    pub healthy: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DesktopRuntimeTrialReport {
+    // Started fully (not degraded), passed the health probes if they ran, and stopped again.
+    pub ok: bool,
+    pub profile_errors: Vec<DesktopRuntimeProfileIssue>,
+    pub profile_warnings: Vec<DesktopRuntimeProfileIssue>,
+    pub started: bool,
+    pub degraded: bool,
+    pub ready: Option<DesktopRuntimeReady>,
+    pub health: Option<DesktopRuntimeHealth>,
+    pub stopped: bool,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+    // Everything logged from the start of the trial through its stop.
+    pub events: Vec<DesktopRuntimeDiagEvent>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DesktopRuntimeHealth {
+    pub checked_at_ms: u64,
+    pub mode: String,
+    pub healthy: bool,
+    pub probes: Vec<DesktopRuntimeProbe>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DesktopRuntimeLogChunk {
+    pub service: String,
+    pub path: String,
+    pub from_offset: u64,
+    pub next_offset: u64,
+    pub reset: bool,
+    pub file_id: String,
+    pub data: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DesktopRuntimeBackendLogs {
+    pub url: String,
+    pub status: u16,
+    pub body: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DesktopRuntimePortScan {
+    pub start: u16,
+    pub end: u16,
+    pub free: Vec<u16>,
+    // The addresses each port was bind-tested on.
+    pub hosts: Vec<String>,
+    pub truncated: bool,
+    pub note: Option<String>,
+}
+
+// One record per successful launch, for dashboards; emitted at the end of `start`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DesktopRuntimeReady {
+    pub ts_ms: u64,
+    pub mode: String,
+    pub web_port: u16,
+    pub backend_port: u16,
+    pub mongo_port: u16,
+    pub backend_url: String,
+    pub ready_ms: BTreeMap<String, u64>,
+    // None when no Mongo sidecar is managed.
+    pub mongo_fresh: Option<bool>,
+    pub startup_ms: u64,
+}
+
+// One message on the `start_streaming` channel. `kind` is progress, log, ready, degraded,
+// failed or done; the last three end the stream and nothing is sent after them.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DesktopRuntimeStartEvent {
+    pub kind: String,
+    pub ts_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub progress: Option<DesktopRuntimeStartProgress>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event: Option<DesktopRuntimeDiagEvent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ready: Option<DesktopRuntimeReady>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl DesktopRuntimeStartEvent {
+    fn new(kind: &str) -> Self {
+        Self {
+            kind: kind.to_string(),
+            ts_ms: now_ms(),
+            progress: None,
+            event: None,
+            ready: None,
+            error: None,
+        }
+    }
+}
+
+pub type StartEventSink = Arc<dyn Fn(&DesktopRuntimeStartEvent) + Send + Sync>;
+
+// Receives every diagnostics event pushed while a streaming start runs.
+#[derive(Clone)]
+struct StartStream(StartEventSink);
+
+impl std::fmt::Debug for StartStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("StartStream")
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DesktopRuntimeSignalResult {
+    pub service: String,
+    pub signal: String,
+    pub pid: u32,
+    pub delivered: bool,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DesktopRuntimeTaskResult {
+    pub name: String,
+    pub exit_code: i32,
+    pub duration_ms: u64,
+    pub output: String,
+}
+
+// Stored and serialized in canonical lowercase form; legacy/alias spellings
+// ("warning", "err", "fatal") are mapped when events are pushed or loaded from disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "String", from = "String")]
+pub enum EventLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl EventLevel {
+    fn from_raw(value: &str) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "trace" => Self::Trace,
+            "debug" => Self::Debug,
+            "warn" | "warning" => Self::Warn,
+            "error" | "err" | "fatal" | "critical" => Self::Error,
+            _ => Self::Info,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Trace => "trace",
+            Self::Debug => "debug",
+            Self::Info => "info",
+            Self::Warn => "warn",
+            Self::Error => "error",
+        }
+    }
+
+    fn rank(self) -> u8 {
+        match self {
+            Self::Trace => 0,
+            Self::Debug => 1,
+            Self::Info => 2,
+            Self::Warn => 3,
+            Self::Error => 4,
+        }
+    }
+}
+
+// `PQA_LOG=watchdog=warn,runtime=info`: per-source thresholds for the shell's own events,
+// applied on top of the profile's `min_event_level`. A bare level sets the default for
+// sources not listed; entries that don't parse are ignored. Errors are never dropped.
+#[derive(Debug, Clone, Default)]
+struct EventSourceFilter {
+    default: Option<EventLevel>,
+    sources: BTreeMap<String, EventLevel>,
+}
+
+impl EventSourceFilter {
+    fn parse(raw: &str) -> Self {
+        let strict_level = |value: &str| match value.trim().to_lowercase().as_str() {
+            "trace" => Some(EventLevel::Trace),
+            "debug" => Some(EventLevel::Debug),
+            "info" => Some(EventLevel::Info),
+            "warn" | "warning" => Some(EventLevel::Warn),
+            "error" => Some(EventLevel::Error),
+            _ => None,
+        };
+        let mut filter = Self::default();
+        for directive in raw.split(',').map(str::trim).filter(|value| !value.is_empty()) {
+            match directive.split_once('=') {
+                Some((source, level)) => {
+                    if let Some(level) = strict_level(level) {
+                        let source = EventSource::from_raw(source);
+                        filter.sources.insert(source.as_str().to_string(), level);
+                    }
+                }
+                None => {
+                    if let Some(level) = strict_level(directive) {
+                        filter.default = Some(level);
+                    }
+                }
+            }
+        }
+        filter
+    }
+
+    fn from_env() -> Self {
+        env::var("PQA_LOG").map(|raw| Self::parse(&raw)).unwrap_or_default()
+    }
+
+    fn allows(&self, source: &EventSource, level: EventLevel) -> bool {
+        let threshold = self.sources.get(source.as_str()).copied().or(self.default);
+        match threshold {
+            Some(threshold) => level == EventLevel::Error || level.rank() >= threshold.rank(),
+            None => true,
+        }
+    }
+}
+
+impl From<String> for EventLevel {
+    fn from(value: String) -> Self {
+        Self::from_raw(&value)
+    }
+}
+
+impl From<EventLevel> for String {
+    fn from(value: EventLevel) -> Self {
+        value.as_str().to_string()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "String", from = "String")]
+pub enum EventSource {
+    Runtime,
+    Watchdog,
+    Preflight,
+    Watch,
+    Task,
+    Web,
+    Backend,
+    Mongo,
+    Other(String),
+}
+
+impl EventSource {
+    fn from_raw(value: &str) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "runtime" => Self::Runtime,
+            "watchdog" => Self::Watchdog,
+            "preflight" => Self::Preflight,
+            "watch" => Self::Watch,
+            "task" => Self::Task,
+            "web" => Self::Web,
+            "backend" => Self::Backend,
+            "mongo" | "mongod" => Self::Mongo,
+            other => Self::Other(other.to_string()),
+        }
+    }
+
+    fn as_str(&self) -> &str {
+        match self {
+            Self::Runtime => "runtime",
+            Self::Watchdog => "watchdog",
+            Self::Preflight => "preflight",
+            Self::Watch => "watch",
+            Self::Task => "task",
+            Self::Web => "web",
+            Self::Backend => "backend",
+            Self::Mongo => "mongo",
+            Self::Other(value) => value.as_str(),
+        }
+    }
+}
+
+impl From<String> for EventSource {
+    fn from(value: String) -> Self {
+        Self::from_raw(&value)
+    }
+}
+
+impl From<EventSource> for String {
+    fn from(value: EventSource) -> Self {
+        value.as_str().to_string()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DesktopRuntimeDiagEvent {
+    pub ts_ms: u64,
+    pub level: EventLevel,
+    pub source: EventSource,
+    pub message: String,
+    #[serde(default)]
+    pub session_id: Option<String>,
+    // External ID from the start request, for stitching shell events to backend/test traces.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance_label: Option<String>,
+    // Machine-readable context (service, port, code, duration_ms, ...); `message` stays the summary.
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub fields: serde_json::Map<String, serde_json::Value>,
+}
+
+// One entry per launched sidecar in the last stop. `forced` means it was killed: it had no
+// grace period, ignored SIGTERM or ran past `stop_total_timeout_ms`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DesktopRuntimeServiceStop {
+    pub service: String,
+    pub was_running: bool,
+    pub graceful: bool,
+    pub forced: bool,
+    pub exit_observed: bool,
+    pub stop_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DesktopRuntimeLastError {
+    pub error: Option<String>,
+    // None when the error is only known from the status (`last_error`), not as an event.
+    pub ts_ms: Option<u64>,
+    // The error event with up to `around` events on each side, oldest first.
+    pub context: Vec<DesktopRuntimeDiagEvent>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DesktopRuntimeDiagnostics {
+    pub generated_at_ms: u64,
+    pub status: DesktopRuntimeStatus,
+    pub events: Vec<DesktopRuntimeDiagEvent>,
+    // Events matching the filters before `limit` was applied, for paging.
+    pub matched: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct RestartRecord {
+    pub ts_ms: u64,
+    pub service: String,
+    pub reason: String,
+    pub success: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DesktopRuntimeServiceSample {
+    // Worst state held during the bucket: down, unhealthy, up, stopped or unknown.
+    pub state: String,
+    pub exits: u32,
+    pub restarts: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DesktopRuntimeHealthBucket {
+    pub start_ms: u64,
+    pub services: BTreeMap<String, DesktopRuntimeServiceSample>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DesktopRuntimeHealthTimeline {
+    pub from_ms: u64,
+    pub to_ms: u64,
+    pub bucket_ms: u64,
+    pub buckets: Vec<DesktopRuntimeHealthBucket>,
+}
+
+// One kind of event (source, level and message with the numbers masked) in a diagnostics
+// diff. Offsets are from the first event of each file.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DesktopRuntimeDiffEntry {
+    pub source: String,
+    pub level: String,
+    pub pattern: String,
+    pub a_count: usize,
+    pub b_count: usize,
+    pub a_offset_ms: Option<u64>,
+    pub b_offset_ms: Option<u64>,
+    pub a_example: Option<String>,
+    pub b_example: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DesktopRuntimeDiagnosticsDiff {
+    pub a_path: String,
+    pub b_path: String,
+    pub a_events: usize,
+    pub b_events: usize,
+    pub a_span_ms: u64,
+    pub b_span_ms: u64,
+    // Only in b, only in a, and in both but a different number of times.
+    pub added: Vec<DesktopRuntimeDiffEntry>,
+    pub removed: Vec<DesktopRuntimeDiffEntry>,
+    pub changed: Vec<DesktopRuntimeDiffEntry>,
+    // Every kind of event in the order it first showed up in either run.
+    pub timeline: Vec<DesktopRuntimeDiffEntry>,
+    // A list hit MAX_DIFF_ENTRIES.
+    pub truncated: bool,
+}
+
+#[derive(Debug)]
+struct RuntimeProcessState {
+    running: bool,
+    mode: RuntimeMode,
+    web: Option<Child>,
+    backend: Option<Child>,
+    backend_replicas: Vec<Option<Child>>,
+    mongo: Option<Child>,
+    started_at_ms: Option<u64>,
+    last_error: Option<String>,
+    web_port: u16,
+    backend_port: u16,
+    mongo_port: u16,
+    backend_url: String,
+    auto_restart: bool,
+    restart_count: u32,
+    last_restart_ms: Option<u64>,
+    launch_config: Option<RuntimeLaunchConfig>,
+    events: EventRing,
+    diagnostics_path: Option<PathBuf>,
+    last_reconcile_ms: Option<u64>,
+    launch_generation: u64,
+    auto_restart_disabled_ms: Option<u64>,
+    last_crash_ms: Option<u64>,
+    failed_restart_ms: Vec<u64>,
+    escalated_restart_ms: Option<u64>,
+    session_path: Option<PathBuf>,
+    port_adjusted: bool,
+    watch_fingerprint: Option<WatchFingerprint>,
+    watch_pending_since_ms: Option<u64>,
+    last_log_budget_check_ms: Option<u64>,
+    log_budget_exceeded: bool,
+    ready_durations_ms: BTreeMap<&'static str, u64>,
+    start_stream: Option<StartStream>,
+    // Observe-only: an externally started stack is probed, never spawned, restarted or killed.
+    observe_only: bool,
+    watchdog_suspended: bool,
+    on_ready_running: bool,
+    // Profile file and its contents as of the start, for drift checks.
+    launch_profile: Option<(PathBuf, serde_json::Value)>,
+    profile_checked_mtime: Option<SystemTime>,
+    last_drift_check_ms: Option<u64>,
+    config_drift_fields: Vec<String>,
+    last_stop: Vec<DesktopRuntimeServiceStop>,
+    observed_probes: Vec<DesktopRuntimeProbe>,
+    last_observe_ms: Option<u64>,
+    backend_health: Option<serde_json::Value>,
+    // What each sidecar was last spawned with, for `commandlines`.
+    launched_commands: BTreeMap<String, DesktopRuntimeCommandPreview>,
+    parked_services: BTreeSet<&'static str>,
+    restarting_services: BTreeSet<&'static str>,
+    // Services found already listening and used as-is; they have no Child and are not ours to kill.
+    adopted_services: BTreeSet<&'static str>,
+    last_health_check_ms: Option<u64>,
+    unhealthy_streaks: BTreeMap<&'static str, u32>,
+    unhealthy_restart_count: u32,
+    last_exit: BTreeMap<&'static str, String>,
+    restart_history: Vec<RestartRecord>,
+    workspace_root: Option<PathBuf>,
+    trial_profile_path: Option<PathBuf>,
+    web_dir: Option<PathBuf>,
+    backend_dir: Option<PathBuf>,
+    desktop_session_id: Option<String>,
+    correlation_id: Option<String>,
+    instance_label: Option<String>,
+    min_event_level: EventLevel,
+    log_to_stderr: bool,
+    // Off: events live only in memory, nothing is read from or written to runtime-events.json.
+    persist_diagnostics: bool,
+    source_filter: EventSourceFilter,
+    start_phase: Option<String>,
+    start_progress: f32,
+    mongo_dbpath: Option<PathBuf>,
+    mongo_fresh: bool,
+    runtime_scope: Option<String>,
+    diagnostics_path_explicit: bool,
+    data_dir_override: Option<String>,
+    launch_warnings: Vec<String>,
+}
+
+impl Default for RuntimeProcessState {
+    fn default() -> Self {
+        Self {
+            running: false,
+            mode: RuntimeMode::LocalFullstack,
+            web: None,
+            backend: None,
+            backend_replicas: Vec::new(),
+            mongo: None,
+            started_at_ms: None,
+            last_error: None,
+            web_port: 3000,
+            backend_port: 8080,
+            mongo_port: 27017,
+            backend_url: "http://127.0.0.1:8080".to_string(),
+            auto_restart: false,
+            restart_count: 0,
+            last_restart_ms: None,
+            launch_config: None,
+            events: EventRing::default(),
+            diagnostics_path: None,
+            last_reconcile_ms: None,
+            launch_generation: 0,
+            auto_restart_disabled_ms: None,
+            last_crash_ms: None,
+            failed_restart_ms: Vec::new(),
+            escalated_restart_ms: None,
+            session_path: None,
+            port_adjusted: false,
+            watch_fingerprint: None,
+            watch_pending_since_ms: None,
+            last_log_budget_check_ms: None,
+            log_budget_exceeded: false,
+            ready_durations_ms: BTreeMap::new(),
+            start_stream: None,
+            observe_only: false,
+            watchdog_suspended: false,
+            on_ready_running: false,
+            launch_profile: None,
+            profile_checked_mtime: None,
+            last_drift_check_ms: None,
+            config_drift_fields: Vec::new(),
+            last_stop: Vec::new(),
+            observed_probes: Vec::new(),
+            last_observe_ms: None,
+            backend_health: None,
+            launched_commands: BTreeMap::new(),
+            parked_services: BTreeSet::new(),
+            restarting_services: BTreeSet::new(),
+            adopted_services: BTreeSet::new(),
+            last_health_check_ms: None,
+            unhealthy_streaks: BTreeMap::new(),
+            unhealthy_restart_count: 0,
+            last_exit: BTreeMap::new(),
+            restart_history: Vec::new(),
+            workspace_root: None,
+            trial_profile_path: None,
+            web_dir: None,
+            backend_dir: None,
+            desktop_session_id: None,
+            correlation_id: None,
+            instance_label: None,
+            min_event_level: EventLevel::Trace,
+            log_to_stderr: env_log_to_stderr().unwrap_or(false),
+            persist_diagnostics: env_persist_diagnostics().unwrap_or(true),
+            source_filter: EventSourceFilter::from_env(),
+            start_phase: None,
+            start_progress: 0.0,
+            mongo_dbpath: None,
+            mongo_fresh: false,
+            runtime_scope: None,
+            diagnostics_path_explicit: false,
+            data_dir_override: None,
+            launch_warnings: Vec::new(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct RuntimeController {
+    state: Arc<Mutex<RuntimeProcessState>>,
+    last_status: Mutex<Option<DesktopRuntimeStatus>>,
+    // Set for the whole of a start so a second one is refused instead of spawning over it.
+    // Outside `state` on purpose: a start holds that lock until it is done, so a flag in there
+    // could only be read once the first start had finished.
+    starting: AtomicBool,
+}
+
+impl RuntimeController {
+    fn remember_status(&self, status: DesktopRuntimeStatus) -> DesktopRuntimeStatus {
+        if let Ok(mut cached) = self.last_status.lock() {
+            *cached = Some(status.clone());
+        }
+        status
+    }
+
+    fn cached_status(&self) -> DesktopRuntimeStatus {
+        self.last_status
+            .lock()
+            .ok()
+            .and_then(|cached| cached.clone())
+            .unwrap_or_else(|| snapshot_status(&RuntimeProcessState::default()))
+    }
+}
+
+fn lock_runtime(shared: &Mutex<RuntimeProcessState>) -> MutexGuard<'_, RuntimeProcessState> {
+    shared.lock().expect("desktop runtime mutex poisoned")
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn normalize_path(raw: &str) -> Option<PathBuf> {
+    let trimmed = raw.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+    Some(PathBuf::from(trimmed))
+}
+
+fn user_home_dir() -> Option<PathBuf> {
+    if let Ok(raw) = env::var("HOME") {
+        let clean = raw.trim();
+        if !clean.is_empty() {
+            return Some(PathBuf::from(clean));
+        }
+    }
+    if let Ok(raw) = env::var("USERPROFILE") {
+        let clean = raw.trim();
+        if !clean.is_empty() {
+            return Some(PathBuf::from(clean));
+        }
+    }
+    None
+}
+
+fn expand_tilde_path(raw: &str) -> PathBuf {
+    let text = raw.trim();
+    if text == "~" {
+        if let Some(home) = user_home_dir() {
+            return home;
+        }
+    }
+    if let Some(rest) = text.strip_prefix("~/") {
+        if let Some(home) = user_home_dir() {
+            return home.join(rest);
+        }
+    }
+    PathBuf::from(text)
+}
+
+fn expand_env_vars(raw: &str) -> String {
+    let mut out = String::with_capacity(raw.len());
+    let mut rest = raw;
+    while let Some(pos) = rest.find('$') {
+        out.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+        let (name, consumed) = if let Some(braced) = after.strip_prefix('{') {
+            match braced.find('}') {
+                Some(end) => (&braced[..end], end + 2),
+                None => ("", 0),
+            }
+        } else {
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            (&after[..end], end)
+        };
+        if name.is_empty() {
+            out.push('$');
+            rest = after;
+            continue;
+        }
+        out.push_str(&env::var(name).unwrap_or_default());
+        rest = &after[consumed..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn expand_user_path(raw: &str) -> PathBuf {
+    expand_tilde_path(&expand_env_vars(raw))
+}
+
+fn explicit_diagnostics_path(raw: Option<&str>) -> Result<Option<PathBuf>, String> {
+    let Some(raw) = raw.filter(|value| !value.trim().is_empty()) else {
+        return Ok(None);
+    };
+    let path = expand_user_path(raw);
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(|err| {
+            format!(
+                "diagnostics_path parent {} cannot be created: {err}",
+                parent.display()
+            )
+        })?;
+    }
+    Ok(Some(path))
+}
+
+fn env_data_dir() -> Option<String> {
+    env::var("PQA_DATA_DIR").ok().filter(|raw| !raw.trim().is_empty())
+}
+
+// The platform app data dir (AppData, Library/Application Support, XDG) as resolved by the
+// Tauri shell; unset for headless consumers, which keep the home-based default.
+static APP_DATA_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+// Set when neither PQA_DATA_DIR, an app data dir nor a home dir is available and data lands
+// under the CWD.
+fn cwd_data_dir_warning() -> Option<String> {
+    if env_data_dir().is_some() || APP_DATA_DIR.get().is_some() || user_home_dir().is_some() {
+        return None;
+    }
+    let root = env::current_dir()
+        .map(|cwd| cwd.join(".project-qa-assistant"))
+        .unwrap_or_else(|_| PathBuf::from(".project-qa-assistant"));
+    Some(format!(
+        "No home dir found (HOME and USERPROFILE unset) and no PQA_DATA_DIR or data_dir set; runtime data goes to {}",
+        root.display()
+    ))
+}
+
+fn runtime_dir_for_data_dir(data_dir_hint: Option<&str>) -> PathBuf {
+    let root = match data_dir_hint.filter(|raw| !raw.trim().is_empty()).map(str::to_string).or_else(env_data_dir) {
+        Some(raw) => expand_tilde_path(&raw),
+        None => {
+            if let Some(app_data_dir) = APP_DATA_DIR.get() {
+                app_data_dir.clone()
+            } else if let Some(home) = user_home_dir() {
+                home.join(".project-qa-assistant")
+            } else if let Ok(cwd) = env::current_dir() {
+                cwd.join(".project-qa-assistant")
+            } else {
+                PathBuf::from(".project-qa-assistant")
+            }
+        }
+    };
+    root.join("runtime")
+}
+
+// Stable across runs (unlike std's DefaultHasher) so a profile keeps its runtime dir.
+fn fnv1a_hash(raw: &str) -> u32 {
+    raw.bytes()
+        .fold(0x811c_9dc5_u32, |hash, byte| (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193))
+}
+
+// An explicit session id wins; otherwise the profile path identifies the instance. Without
+// either the legacy flat `runtime/` layout is used.
+fn runtime_scope_for(explicit_session_id: Option<&str>, profile_path: &str) -> Option<String> {
+    if let Some(session) = explicit_session_id.map(sanitize_archive_label).filter(|value| !value.is_empty()) {
+        return Some(session);
+    }
+    let profile_path = profile_path.trim();
+    if profile_path.is_empty() {
+        return None;
+    }
+    let stem = Path::new(profile_path)
+        .file_stem()
+        .map(|stem| sanitize_archive_label(&stem.to_string_lossy()))
+        .filter(|value| !value.is_empty())
+        .unwrap_or_else(|| "profile".to_string());
+    Some(format!("{stem}-{:08x}", fnv1a_hash(profile_path)))
+}
+
+fn scoped_runtime_dir(data_dir_hint: Option<&str>, scope: Option<&str>) -> PathBuf {
+    let runtime_dir = runtime_dir_for_data_dir(data_dir_hint);
+    match scope {
+        Some(scope) => runtime_dir.join("sessions").join(scope),
+        None => runtime_dir,
+    }
+}
+
+// The first scoped session adopts the flat-layout files so an existing single-session setup
+// keeps its event history and restart budget; once several sessions exist nothing is moved.
+fn migrate_legacy_runtime_dir(legacy_dir: &Path, scoped_dir: &Path) -> Option<String> {
+    if scoped_dir == legacy_dir || scoped_dir.exists() {
+        return None;
+    }
+    let sessions_dir = scoped_dir.parent()?;
+    let other_sessions = fs::read_dir(sessions_dir)
+        .map(|entries| entries.flatten().any(|entry| entry.path().is_dir()))
+        .unwrap_or(false);
+    if other_sessions {
+        return None;
+    }
+    let legacy_entries = ["runtime-events.json", "runtime-session.json", "logs"];
+    if !legacy_entries.iter().any(|name| legacy_dir.join(name).exists()) {
+        return None;
+    }
+    fs::create_dir_all(scoped_dir).ok()?;
+    let moved: Vec<&str> = legacy_entries
+        .into_iter()
+        .filter(|name| {
+            let from = legacy_dir.join(name);
+            from.exists() && fs::rename(&from, scoped_dir.join(name)).is_ok()
+        })
+        .collect();
+    if moved.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "Migrated legacy runtime files ({}) into {}",
+        moved.join(", "),
+        scoped_dir.display()
+    ))
+}
+
+fn diagnostics_path_for_data_dir(data_dir_hint: Option<&str>) -> PathBuf {
+    runtime_dir_for_data_dir(data_dir_hint).join("runtime-events.json")
+}
+
+fn log_dir_for_data_dir(data_dir_hint: Option<&str>) -> PathBuf {
+    runtime_dir_for_data_dir(data_dir_hint).join("logs")
+}
+
+fn load_runtime_events_from_path(path: &Path) -> EventRing {
+    let mut ring = EventRing::default();
+    if let Ok(rows) = fs::read_to_string(path)
+        .map_err(|err| err.to_string())
+        .and_then(|raw| serde_json::from_str::<Vec<DesktopRuntimeDiagEvent>>(&raw).map_err(|err| err.to_string()))
+    {
+        ring.extend(rows);
+    }
+    ring
+}
+
+const MAX_EVENTS: usize = 200;
+const MAX_SEVERE_EVENTS: usize = 500;
+const MAX_DIFF_ENTRIES: usize = 200;
+
+// An event file (`runtime-events.json` or an archive) is an array of events; a saved
+// `desktop_runtime_diagnostics` result has them under `events`.
+fn read_diagnostics_export(path: &Path) -> Result<Vec<DesktopRuntimeDiagEvent>, String> {
+    let raw = fs::read_to_string(path).map_err(|err| format!("cannot read {}: {err}", path.display()))?;
+    let value = serde_json::from_str::<serde_json::Value>(&raw)
+        .map_err(|err| format!("{} is not JSON: {err}", path.display()))?;
+    let rows = match value {
+        serde_json::Value::Object(mut object) => object.remove("events").unwrap_or(serde_json::Value::Null),
+        other => other,
+    };
+    let mut events = serde_json::from_value::<Vec<DesktopRuntimeDiagEvent>>(rows)
+        .map_err(|err| format!("{} holds no diagnostics events: {err}", path.display()))?;
+    events.sort_by_key(|event| event.ts_ms);
+    Ok(events)
+}
+
+// Ports, pids, durations and ids differ between any two runs, so every digit run counts as one.
+fn event_pattern(message: &str) -> String {
+    let mut masked = String::with_capacity(message.len());
+    let mut in_number = false;
+    for c in message.chars() {
+        if c.is_ascii_digit() {
+            if !in_number {
+                masked.push('#');
+            }
+            in_number = true;
+        } else {
+            in_number = false;
+            masked.push(c);
+        }
+    }
+    masked.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn diff_diagnostics(a_path: &Path, b_path: &Path) -> Result<DesktopRuntimeDiagnosticsDiff, String> {
+    let a = read_diagnostics_export(a_path)?;
+    let b = read_diagnostics_export(b_path)?;
+    let span = |events: &[DesktopRuntimeDiagEvent]| match (events.first(), events.last()) {
+        (Some(first), Some(last)) => last.ts_ms.saturating_sub(first.ts_ms),
+        _ => 0,
+    };
+    let mut entries: BTreeMap<(String, String, String), DesktopRuntimeDiffEntry> = BTreeMap::new();
+    for (events, in_a) in [(&a, true), (&b, false)] {
+        let origin = events.first().map(|event| event.ts_ms).unwrap_or(0);
+        for event in events.iter() {
+            let key = (
+                event.source.as_str().to_string(),
+                event.level.as_str().to_string(),
+                event_pattern(&event.message),
+            );
+            let entry = entries.entry(key.clone()).or_insert_with(|| DesktopRuntimeDiffEntry {
+                source: key.0,
+                level: key.1,
+                pattern: key.2,
+                a_count: 0,
+                b_count: 0,
+                a_offset_ms: None,
+                b_offset_ms: None,
+                a_example: None,
+                b_example: None,
+            });
+            let offset = event.ts_ms.saturating_sub(origin);
+            let (count, first_offset, example) = if in_a {
+                (&mut entry.a_count, &mut entry.a_offset_ms, &mut entry.a_example)
+            } else {
+                (&mut entry.b_count, &mut entry.b_offset_ms, &mut entry.b_example)
+            };
+            *count += 1;
+            first_offset.get_or_insert(offset);
+            example.get_or_insert_with(|| event.message.clone());
+        }
+    }
+    let mut timeline: Vec<DesktopRuntimeDiffEntry> = entries.into_values().collect();
+    timeline.sort_by_key(|entry| {
+        entry
+            .a_offset_ms
+            .into_iter()
+            .chain(entry.b_offset_ms)
+            .min()
+            .unwrap_or(0)
+    });
+    let mut truncated = false;
+    let mut pick = |keep: &dyn Fn(&DesktopRuntimeDiffEntry) -> bool| {
+        let picked: Vec<DesktopRuntimeDiffEntry> = timeline.iter().filter(|entry| keep(entry)).cloned().collect();
+        truncated |= picked.len() > MAX_DIFF_ENTRIES;
+        picked.into_iter().take(MAX_DIFF_ENTRIES).collect::<Vec<_>>()
+    };
+    let added = pick(&|entry| entry.a_count == 0);
+    let removed = pick(&|entry| entry.b_count == 0);
+    let changed = pick(&|entry| entry.a_count > 0 && entry.b_count > 0 && entry.a_count != entry.b_count);
+    truncated |= timeline.len() > MAX_DIFF_ENTRIES;
+    timeline.truncate(MAX_DIFF_ENTRIES);
+    Ok(DesktopRuntimeDiagnosticsDiff {
+        a_path: a_path.display().to_string(),
+        b_path: b_path.display().to_string(),
+        a_events: a.len(),
+        b_events: b.len(),
+        a_span_ms: span(&a),
+        b_span_ms: span(&b),
+        added,
+        removed,
+        changed,
+        timeline,
+        truncated,
+    })
+}
+
+// The in-memory event feed. warn/error events have their own, larger cap so a flood of
+// info/debug noise cannot evict the error that started a cascade, so each class lives in its
+// own deque and drops its oldest event in O(1); entries carry a sequence number so `iter`
+// can merge the two back into insertion order.
+#[derive(Debug, Default)]
+struct EventRing {
+    severe: VecDeque<(u64, DesktopRuntimeDiagEvent)>,
+    other: VecDeque<(u64, DesktopRuntimeDiagEvent)>,
+    next_seq: u64,
+}
+
+impl EventRing {
+    fn push(&mut self, event: DesktopRuntimeDiagEvent) {
+        let seq = self.next_seq;
+        self.next_seq = self.next_seq.wrapping_add(1);
+        let (class, cap) = if event.level.rank() >= EventLevel::Warn.rank() {
+            (&mut self.severe, MAX_SEVERE_EVENTS)
+        } else {
+            (&mut self.other, MAX_EVENTS)
+        };
+        if class.len() >= cap {
+            class.pop_front();
+        }
+        class.push_back((seq, event));
+    }
+
+    fn extend(&mut self, events: impl IntoIterator<Item = DesktopRuntimeDiagEvent>) {
+        for event in events {
+            self.push(event);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.severe.len() + self.other.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.severe.is_empty() && self.other.is_empty()
+    }
+
+    fn clear(&mut self) {
+        self.severe.clear();
+        self.other.clear();
+    }
+
+    // Oldest first.
+    fn iter(&self) -> impl Iterator<Item = &DesktopRuntimeDiagEvent> + '_ {
+        let mut severe = self.severe.iter().peekable();
+        let mut other = self.other.iter().peekable();
+        std::iter::from_fn(move || {
+            let take_severe = match (severe.peek(), other.peek()) {
+                (Some((a, _)), Some((b, _))) => a < b,
+                (Some(_), None) => true,
+                (None, _) => false,
+            };
+            let next = if take_severe { severe.next() } else { other.next() };
+            next.map(|(_, event)| event)
+        })
+    }
+
+    fn to_vec(&self) -> Vec<DesktopRuntimeDiagEvent> {
+        self.iter().cloned().collect()
+    }
+}
+
+fn persist_runtime_events(state: &RuntimeProcessState) {
+    let Some(path) = state.diagnostics_path.as_ref().filter(|_| state.persist_diagnostics) else {
+        return;
+    };
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    if let Ok(payload) = serde_json::to_string(&state.events.iter().collect::<Vec<_>>()) {
+        let _ = fs::write(path, payload);
+    }
+}
+
+fn persist_runtime_session(state: &RuntimeProcessState) {
+    let Some(path) = state.session_path.as_ref() else {
+        return;
+    };
+    let record = RuntimeSessionRecord {
+        updated_at_ms: now_ms(),
+        desktop_session_id: state
+            .launch_config
+            .as_ref()
+            .map(|config| config.desktop_session_id.clone()),
+        instance_label: state.instance_label.clone(),
+        web_pid: state.web.as_ref().map(|c| c.id()),
+        backend_pid: state.backend.as_ref().map(|c| c.id()),
+        mongo_pid: state.mongo.as_ref().map(|c| c.id()),
+        restart_count: state.restart_count,
+        last_restart_ms: state.last_restart_ms,
+        auto_restart: state.auto_restart,
+        auto_restart_disabled_ms: state.auto_restart_disabled_ms,
+    };
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    if let Ok(payload) = serde_json::to_string(&record) {
+        let _ = fs::write(path, payload);
+    }
+}
+
+fn load_runtime_session(path: &Path) -> Option<RuntimeSessionRecord> {
+    let raw = fs::read_to_string(path).ok()?;
+    serde_json::from_str::<RuntimeSessionRecord>(&raw).ok()
+}
+
+// A crash loop that spans an app relaunch keeps its restart budget instead of starting over.
+fn restore_restart_budget(state: &mut RuntimeProcessState) {
+    let Some(record) = state.session_path.as_deref().and_then(load_runtime_session) else {
+        return;
+    };
+    let now = now_ms();
+    let within_window = |ts: Option<u64>| {
+        ts.map(|value| now.saturating_sub(value) < RESTART_WINDOW_MS)
+            .unwrap_or(false)
+    };
+    if within_window(record.last_restart_ms) && record.restart_count > 0 {
+        state.restart_count = record.restart_count;
+        state.last_restart_ms = record.last_restart_ms;
+        push_runtime_event(
+            state,
+            "warn",
+            "runtime",
+            format!(
+                "Restored restart budget from previous session: {} restarts in the last {}s",
+                record.restart_count,
+                RESTART_WINDOW_MS / 1000
+            ),
+        );
+    }
+    if within_window(record.auto_restart_disabled_ms) {
+        state.auto_restart = false;
+        state.auto_restart_disabled_ms = record.auto_restart_disabled_ms;
+        push_runtime_event(
+            state,
+            "warn",
+            "runtime",
+            "Auto-restart stays disabled: previous session hit the restart budget",
+        );
+    }
+}
+
+fn ensure_diagnostics_state(state: &mut RuntimeProcessState, data_dir_hint: Option<&str>) {
+    let next_path = if let Some(raw) = data_dir_hint {
+        if raw.trim().is_empty() {
+            state
+                .diagnostics_path
+                .clone()
+                .unwrap_or_else(|| diagnostics_path_for_data_dir(None))
+        } else {
+            diagnostics_path_for_data_dir(Some(raw))
+        }
+    } else {
+        state
+            .diagnostics_path
+            .clone()
+            .unwrap_or_else(|| diagnostics_path_for_data_dir(None))
+    };
+    switch_diagnostics_path(state, next_path);
+}
+
+fn switch_diagnostics_path(state: &mut RuntimeProcessState, next_path: PathBuf) {
+    if !state.persist_diagnostics {
+        return;
+    }
+    let changed = state
+        .diagnostics_path
+        .as_ref()
+        .map(|current| current != &next_path)
+        .unwrap_or(true);
+    if changed {
+        let mut loaded = load_runtime_events_from_path(&next_path);
+        loaded.extend(state.events.iter().cloned());
+        state.events = loaded;
+        state.diagnostics_path = Some(next_path);
+        persist_runtime_events(state);
+        return;
+    }
+    if state.events.is_empty() {
+        if let Some(path) = state.diagnostics_path.as_ref() {
+            state.events = load_runtime_events_from_path(path);
+        }
+    }
+}
+
+fn sanitize_archive_label(raw: &str) -> String {
+    raw.trim()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+        .collect::<String>()
+        .trim_matches('-')
+        .to_string()
+}
+
+// Moves the current event file to `<runtime>/archive/` and starts a fresh one.
+fn rotate_diagnostics(state: &mut RuntimeProcessState, label: Option<&str>, keep_events: bool) -> Result<PathBuf, String> {
+    if !state.persist_diagnostics {
+        return Err("diagnostics are kept in memory only (persist_diagnostics: false)".to_string());
+    }
+    ensure_diagnostics_state(state, None);
+    let Some(path) = state.diagnostics_path.clone() else {
+        return Err("diagnostics path is not initialized".to_string());
+    };
+    let archive_dir = path
+        .parent()
+        .map(|parent| parent.join("archive"))
+        .unwrap_or_else(|| PathBuf::from("archive"));
+    fs::create_dir_all(&archive_dir)
+        .map_err(|err| format!("failed to create {}: {err}", archive_dir.display()))?;
+    let suffix = label
+        .map(sanitize_archive_label)
+        .filter(|value| !value.is_empty())
+        .map(|value| format!("-{value}"))
+        .unwrap_or_default();
+    let archived = archive_dir.join(format!("runtime-events-{}{suffix}.json", now_ms()));
+    if !path.exists() {
+        persist_runtime_events(state);
+    }
+    fs::rename(&path, &archived)
+        .map_err(|err| format!("failed to archive {}: {err}", path.display()))?;
+    if !keep_events {
+        state.events.clear();
+    }
+    persist_runtime_events(state);
+    Ok(archived)
+}
+
+// The start request's `workspace_root` wins over PQA_WORKSPACE_ROOT, which wins over the
+// checkout this shell was built from. `build` then checks for `web/` and `backend/` under it.
+fn resolve_workspace_root(requested: Option<&str>) -> Result<PathBuf, String> {
+    if let Some(raw) = requested.filter(|raw| !raw.trim().is_empty()) {
+        let path = expand_user_path(raw);
+        if !path.is_dir() {
+            return Err(format!("workspace_root {} is not a directory", path.display()));
+        }
+        return Ok(path);
+    }
+    if let Ok(raw) = env::var("PQA_WORKSPACE_ROOT") {
+        if let Some(path) = normalize_path(&raw) {
+            return Ok(path);
+        }
+    }
+    let from_manifest = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("..")
+        .join("..");
+    Ok(from_manifest)
+}
+
+fn load_runtime_profile(profile_path: Option<&str>) -> RuntimeProfile {
+    let Some(path) = profile_path.and_then(normalize_path) else {
+        return RuntimeProfile::default();
+    };
+    match fs::read_to_string(path) {
+        Ok(raw) => serde_json::from_str::<RuntimeProfile>(&raw).unwrap_or_default(),
+        Err(_) => RuntimeProfile::default(),
+    }
+}
+
+const RUNTIME_PROFILE_SCHEMA: &str = include_str!("../../../desktop/runtime-profile.schema.json");
+
+fn json_pointer_child(pointer: &str, segment: &str) -> String {
+    format!("{pointer}/{}", segment.replace('~', "~0").replace('/', "~1"))
+}
+
+fn json_type_matches(expected: &str, value: &serde_json::Value) -> bool {
+    match expected {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "integer" => value.as_i64().is_some() || value.as_u64().is_some(),
+        "number" => value.is_number(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+const SCHEMA_KEYWORDS: [&str; 16] = [
+    "$schema",
+    "$id",
+    "title",
+    "description",
+    "type",
+    "enum",
+    "minimum",
+    "maximum",
+    "minLength",
+    "maxLength",
+    "pattern",
+    "format",
+    "items",
+    "required",
+    "properties",
+    "additionalProperties",
+];
+
+enum PatternAtom {
+    Any,
+    Char(char),
+    Class { ranges: Vec<(char, char)>, negated: bool },
+}
+
+impl PatternAtom {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            PatternAtom::Any => c != '\n',
+            PatternAtom::Char(expected) => c == *expected,
+            PatternAtom::Class { ranges, negated } => {
+                ranges.iter().any(|(low, high)| (*low..=*high).contains(&c)) != *negated
+            }
+        }
+    }
+}
+
+struct PatternPiece {
+    atom: PatternAtom,
+    min: usize,
+    max: Option<usize>,
+}
+
+// The subset of ECMA-262 patterns the profile schema needs: literals, `.`, classes, `\d`/`\w`/`\s`,
+// the usual quantifiers and `^`/`$` anchors. Groups, alternation and the like are not supported.
+struct SchemaPattern {
+    anchored_start: bool,
+    anchored_end: bool,
+    pieces: Vec<PatternPiece>,
+}
+
+fn escape_class(c: char) -> Option<PatternAtom> {
+    let (ranges, negated) = match c {
+        'd' | 'D' => (vec![('0', '9')], c == 'D'),
+        'w' | 'W' => (vec![('a', 'z'), ('A', 'Z'), ('0', '9'), ('_', '_')], c == 'W'),
+        's' | 'S' => (vec![(' ', ' '), ('\t', '\r')], c == 'S'),
+        _ => return None,
+    };
+    Some(PatternAtom::Class { ranges, negated })
+}
+
+fn parse_bracket_class(chars: &[char], index: &mut usize) -> Option<PatternAtom> {
+    let negated = chars.get(*index) == Some(&'^');
+    if negated {
+        *index += 1;
+    }
+    let mut ranges: Vec<(char, char)> = Vec::new();
+    let mut first = true;
+    loop {
+        let c = *chars.get(*index)?;
+        *index += 1;
+        if c == ']' && !first {
+            return Some(PatternAtom::Class { ranges, negated });
+        }
+        first = false;
+        let low = if c == '\\' {
+            let escaped = *chars.get(*index)?;
+            *index += 1;
+            if let Some(PatternAtom::Class { ranges: nested, negated: false }) = escape_class(escaped) {
+                ranges.extend(nested);
+                continue;
+            }
+            if escaped.is_ascii_alphanumeric() {
+                return None;
+            }
+            escaped
+        } else {
+            c
+        };
+        if chars.get(*index) == Some(&'-') && chars.get(*index + 1).is_some_and(|next| *next != ']') {
+            let high = chars[*index + 1];
+            if high == '\\' || high < low {
+                return None;
+            }
+            *index += 2;
+            ranges.push((low, high));
+        } else {
+            ranges.push((low, low));
+        }
+    }
+}
+
+fn parse_quantifier(chars: &[char], index: &mut usize) -> Option<(usize, Option<usize>)> {
+    let quantifier = match chars.get(*index) {
+        Some('*') => (0, None),
+        Some('+') => (1, None),
+        Some('?') => (0, Some(1)),
+        Some('{') => {
+            let close = chars[*index..].iter().position(|c| *c == '}')? + *index;
+            let body: String = chars[*index + 1..close].iter().collect();
+            let (min, max) = match body.split_once(',') {
+                Some((min, "")) => (min.parse().ok()?, None),
+                Some((min, max)) => (min.parse().ok()?, Some(max.parse().ok()?)),
+                None => {
+                    let exact = body.parse().ok()?;
+                    (exact, Some(exact))
+                }
+            };
+            *index = close;
+            (min, max)
+        }
+        _ => return Some((1, Some(1))),
+    };
+    *index += 1;
+    Some(quantifier)
+}
+
+impl SchemaPattern {
+    fn compile(pattern: &str) -> Option<Self> {
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut index = 0;
+        let anchored_start = chars.first() == Some(&'^');
+        if anchored_start {
+            index = 1;
+        }
+        let mut anchored_end = false;
+        let mut pieces: Vec<PatternPiece> = Vec::new();
+        while index < chars.len() {
+            let c = chars[index];
+            index += 1;
+            let atom = match c {
+                '$' if index == chars.len() => {
+                    anchored_end = true;
+                    break;
+                }
+                '.' => PatternAtom::Any,
+                '[' => parse_bracket_class(&chars, &mut index)?,
+                '\\' => {
+                    let escaped = *chars.get(index)?;
+                    index += 1;
+                    match escape_class(escaped) {
+                        Some(class) => class,
+                        None if escaped.is_ascii_alphanumeric() => return None,
+                        None => PatternAtom::Char(escaped),
+                    }
+                }
+                '(' | ')' | '|' | '^' | '$' | '*' | '+' | '?' | '{' | '}' | ']' => return None,
+                other => PatternAtom::Char(other),
+            };
+            let (min, max) = parse_quantifier(&chars, &mut index)?;
+            if matches!(chars.get(index), Some('?' | '+')) {
+                return None;
+            }
+            pieces.push(PatternPiece { atom, min, max });
+        }
+        Some(Self { anchored_start, anchored_end, pieces })
+    }
+
+    fn is_match(&self, text: &str) -> bool {
+        let chars: Vec<char> = text.chars().collect();
+        let last_start = if self.anchored_start { 0 } else { chars.len() };
+        (0..=last_start).any(|start| self.match_from(0, &chars, start))
+    }
+
+    fn match_from(&self, piece: usize, chars: &[char], pos: usize) -> bool {
+        let Some(current) = self.pieces.get(piece) else {
+            return !self.anchored_end || pos == chars.len();
+        };
+        let available = chars[pos..].iter().take_while(|c| current.atom.matches(**c)).count();
+        let most = current.max.map_or(available, |max| max.min(available));
+        if most < current.min {
+            return false;
+        }
+        (current.min..=most).rev().any(|count| self.match_from(piece + 1, chars, pos + count))
+    }
+}
+
+// Covers the keywords used by runtime-profile.schema.json. Anything it cannot check (another
+// keyword, a pattern outside `SchemaPattern`'s subset) goes to `warnings` instead of passing silently.
+fn validate_against_schema(
+    schema: &serde_json::Value,
+    value: &serde_json::Value,
+    pointer: &str,
+    errors: &mut Vec<DesktopRuntimeProfileIssue>,
+    warnings: &mut Vec<DesktopRuntimeProfileIssue>,
+) {
+    if let Some(keywords) = schema.as_object() {
+        for keyword in keywords.keys().filter(|keyword| !SCHEMA_KEYWORDS.contains(&keyword.as_str())) {
+            warnings.push(DesktopRuntimeProfileIssue {
+                pointer: pointer.to_string(),
+                message: format!("schema keyword {keyword} is not checked"),
+            });
+        }
+    }
+    let mut issue = |message: String| {
+        errors.push(DesktopRuntimeProfileIssue {
+            pointer: pointer.to_string(),
+            message,
+        })
+    };
+    let expected: Vec<&str> = match schema.get("type") {
+        Some(serde_json::Value::String(name)) => vec![name.as_str()],
+        Some(serde_json::Value::Array(names)) => names.iter().filter_map(|name| name.as_str()).collect(),
+        _ => Vec::new(),
+    };
+    if !expected.is_empty() && !expected.iter().any(|name| json_type_matches(name, value)) {
+        issue(format!("expected {}", expected.join(" or ")));
+        return;
+    }
+    if let Some(options) = schema.get("enum").and_then(|e| e.as_array()) {
+        if !options.iter().any(|option| option == value) {
+            let allowed = options.iter().map(|option| option.to_string()).collect::<Vec<_>>().join(", ");
+            issue(format!("must be one of {allowed}"));
+        }
+    }
+    if let Some(number) = value.as_f64() {
+        if let Some(min) = schema.get("minimum").and_then(|m| m.as_f64()) {
+            if number < min {
+                issue(format!("must be >= {min}"));
+            }
+        }
+        if let Some(max) = schema.get("maximum").and_then(|m| m.as_f64()) {
+            if number > max {
+                issue(format!("must be <= {max}"));
+            }
+        }
+    }
+    if let Some(text) = value.as_str() {
+        if let Some(min_len) = schema.get("minLength").and_then(|m| m.as_u64()) {
+            if (text.chars().count() as u64) < min_len {
+                issue(format!("must be at least {min_len} characters"));
+            }
+        }
+        if let Some(max_len) = schema.get("maxLength").and_then(|m| m.as_u64()) {
+            if (text.chars().count() as u64) > max_len {
+                issue(format!("must be at most {max_len} characters"));
+            }
+        }
+        if let Some(pattern) = schema.get("pattern").and_then(|p| p.as_str()) {
+            match SchemaPattern::compile(pattern) {
+                Some(compiled) if !compiled.is_match(text) => issue(format!("must match the pattern {pattern}")),
+                Some(_) => {}
+                None => warnings.push(DesktopRuntimeProfileIssue {
+                    pointer: pointer.to_string(),
+                    message: format!("pattern {pattern} is not supported by the validator; not checked"),
+                }),
+            }
+        }
+        if schema.get("format").and_then(|f| f.as_str()) == Some("uri") && !text.contains("://") {
+            issue("must be an absolute URI".to_string());
+        }
+    }
+    if let Some(items) = value.as_array() {
+        if let Some(item_schema) = schema.get("items") {
+            for (index, item) in items.iter().enumerate() {
+                let item_pointer = json_pointer_child(pointer, &index.to_string());
+                validate_against_schema(item_schema, item, &item_pointer, errors, warnings);
+            }
+        }
+    }
+    let Some(object) = value.as_object() else {
+        return;
+    };
+    if let Some(required) = schema.get("required").and_then(|r| r.as_array()) {
+        for key in required.iter().filter_map(|key| key.as_str()) {
+            if !object.contains_key(key) {
+                errors.push(DesktopRuntimeProfileIssue {
+                    pointer: json_pointer_child(pointer, key),
+                    message: "missing required field".to_string(),
+                });
+            }
+        }
+    }
+    let properties = schema.get("properties").and_then(|p| p.as_object());
+    let additional = schema.get("additionalProperties");
+    for (key, child) in object.iter() {
+        let child_pointer = json_pointer_child(pointer, key);
+        if let Some(child_schema) = properties.and_then(|props| props.get(key)) {
+            validate_against_schema(child_schema, child, &child_pointer, errors, warnings);
+            continue;
+        }
+        match additional {
+            Some(serde_json::Value::Bool(false)) => errors.push(DesktopRuntimeProfileIssue {
+                pointer: child_pointer,
+                message: "unknown field".to_string(),
+            }),
+            Some(extra) if extra.is_object() => {
+                validate_against_schema(extra, child, &child_pointer, errors, warnings)
+            }
+            _ => {}
+        }
+    }
+}
+
+fn validate_runtime_profile_file(path: &str) -> Result<DesktopRuntimeProfileValidation, String> {
+    let resolved = expand_user_path(path);
+    let raw = fs::read_to_string(&resolved)
+        .map_err(|err| format!("failed to read profile {}: {err}", resolved.display()))?;
+    let (errors, warnings) = match serde_json::from_str::<serde_json::Value>(&raw) {
+        Ok(value) => validate_runtime_profile_value(&value)?,
+        Err(err) => {
+            let invalid = DesktopRuntimeProfileIssue {
+                pointer: String::new(),
+                message: format!("invalid JSON: {err}"),
+            };
+            (vec![invalid], Vec::new())
+        }
+    };
+    Ok(DesktopRuntimeProfileValidation {
+        path: resolved.to_string_lossy().to_string(),
+        valid: errors.is_empty(),
+        errors,
+        warnings,
+    })
+}
+
+type ProfileIssues = (Vec<DesktopRuntimeProfileIssue>, Vec<DesktopRuntimeProfileIssue>);
+
+// (errors, warnings)
+fn validate_runtime_profile_value(value: &serde_json::Value) -> Result<ProfileIssues, String> {
+    let schema = serde_json::from_str::<serde_json::Value>(RUNTIME_PROFILE_SCHEMA)
+        .map_err(|err| format!("embedded runtime profile schema is invalid: {err}"))?;
+    let mut errors: Vec<DesktopRuntimeProfileIssue> = Vec::new();
+    let mut warnings: Vec<DesktopRuntimeProfileIssue> = Vec::new();
+    validate_against_schema(&schema, value, "", &mut errors, &mut warnings);
+    Ok((errors, warnings))
+}
+
+fn npm_bin() -> &'static str {
+    if cfg!(target_os = "windows") {
+        "npm.cmd"
+    } else {
+        "npm"
+    }
+}
+
+fn parse_version(raw: &str) -> Option<Vec<u32>> {
+    let start = raw.find(|c: char| c.is_ascii_digit())?;
+    let digits: String = raw[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.')
+        .collect();
+    let parts: Vec<u32> = digits
+        .split('.')
+        .filter(|part| !part.is_empty())
+        .filter_map(|part| part.parse::<u32>().ok())
+        .collect();
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts)
+    }
+}
+
+fn version_at_least(actual: &[u32], minimum: &[u32]) -> bool {
+    let len = actual.len().max(minimum.len());
+    for index in 0..len {
+        let a = actual.get(index).copied().unwrap_or(0);
+        let m = minimum.get(index).copied().unwrap_or(0);
+        if a != m {
+            return a > m;
+        }
+    }
+    true
+}
+
+fn query_tool_version(program: &str) -> Result<String, String> {
+    let output = Command::new(program)
+        .arg("--version")
+        .output()
+        .map_err(|err| format!("failed to run {program} --version: {err}"))?;
+    // Older Pythons print the version on stderr.
+    let text = if output.stdout.is_empty() {
+        String::from_utf8_lossy(&output.stderr).to_string()
+    } else {
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+    Ok(text.trim().to_string())
+}
+
+fn node_bin() -> &'static str {
+    if cfg!(target_os = "windows") {
+        "node.exe"
+    } else {
+        "node"
+    }
+}
+
+fn is_executable_file(path: &Path) -> bool {
+    let Ok(meta) = fs::metadata(path) else {
+        return false;
+    };
+    if !meta.is_file() {
+        return false;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        meta.permissions().mode() & 0o111 != 0
+    }
+    #[cfg(not(unix))]
+    {
+        // Windows runs a file by its extension's association; only PATHEXT types launch directly.
+        let pathext = env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".to_string());
+        let ext = path
+            .extension()
+            .map(|ext| format!(".{}", ext.to_string_lossy()).to_ascii_uppercase())
+            .unwrap_or_default();
+        !ext.is_empty() && pathext.split(';').any(|known| known.trim().eq_ignore_ascii_case(&ext))
+    }
+}
+
+// Candidate files the OS would try for `program`: the path itself when it has a separator
+// (a relative one may resolve against the child's cwd too), otherwise every PATH entry
+// (plus PATHEXT suffixes on Windows).
+fn program_candidates(program: &str, cwd: &Path) -> Vec<PathBuf> {
+    if program.contains('/') || program.contains('\\') {
+        return vec![PathBuf::from(program), cwd.join(program)];
+    }
+    let suffixes: Vec<String> = if cfg!(target_os = "windows") && Path::new(program).extension().is_none() {
+        env::var("PATHEXT")
+            .unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".to_string())
+            .split(';')
+            .map(|ext| ext.trim().to_ascii_lowercase())
+            .filter(|ext| !ext.is_empty())
+            .collect()
+    } else {
+        vec![String::new()]
+    };
+    let path_var = env::var_os("PATH").unwrap_or_default();
+    env::split_paths(&path_var)
+        .flat_map(|dir| suffixes.iter().map(move |ext| dir.join(format!("{program}{ext}"))))
+        .collect()
+}
+
+// Tells a missing binary apart from one that exists but cannot be executed, which otherwise
+// both surface as a generic spawn failure.
+fn check_binary(name: &str, program: &str, cwd: &Path) -> Option<String> {
+    let existing: Vec<PathBuf> = program_candidates(program, cwd)
+        .into_iter()
+        .filter(|candidate| candidate.is_file())
+        .collect();
+    if existing.iter().any(|candidate| is_executable_file(candidate)) {
+        return None;
+    }
+    match existing.first() {
+        Some(path) if cfg!(unix) => Some(format!("{name} binary at {} is not executable (chmod +x?)", path.display())),
+        Some(path) => Some(format!(
+            "{name} binary at {} is not executable (extension not in PATHEXT)",
+            path.display()
+        )),
+        None if program.contains('/') || program.contains('\\') => Some(format!("{name} binary {program} does not exist")),
+        None => Some(format!("{name} binary {program:?} not found in PATH")),
+    }
+}
+
+fn check_sidecar_binaries(config: &RuntimeLaunchConfig) -> Vec<String> {
+    let mut binaries: Vec<(&str, &str, &Path)> = vec![("npm", npm_bin(), config.web_dir.as_path())];
+    if is_backend_required(config) {
+        binaries.push(("python", config.python_bin.as_str(), config.backend_dir.as_path()));
+    }
+    if is_mongo_required(config) {
+        if let Some(mongo_bin) = config.mongo_bin.as_deref() {
+            binaries.push(("mongod", mongo_bin, Path::new(".")));
+        }
+    }
+    binaries
+        .into_iter()
+        .filter_map(|(name, program, cwd)| check_binary(name, program, cwd))
+        .collect()
+}
+
+const DEFAULT_WEB_PORT: u16 = 3000;
+const DEFAULT_BACKEND_PORT: u16 = 8080;
+const DEFAULT_MONGO_PORT: u16 = 27017;
+const DEFAULT_REMOTE_BACKEND_URL: &str = "http://127.0.0.1:8080";
+const DEFAULT_MONGO_URI_ENV: &str = "MONGODB_URI";
+const DEFAULT_PYTHON_BIN: &str = "python3";
+const DEFAULT_MONGOSH_BIN: &str = "mongosh";
+const DEFAULT_MAX_UNHEALTHY: u32 = 3;
+const DEFAULT_BACKEND_URL_ENV: &str = "BACKEND_BASE_URL";
+const DEFAULT_WEB_PROFILE_ARG: &str = "--runtime-profile";
+const DEFAULT_BACKEND_DEBUG_PORT: u16 = 5678;
+// A backend waiting for a debugger is not listening yet, so readiness gets longer.
+const DEFAULT_BACKEND_DEBUG_READY_TIMEOUT_MS: u64 = 10 * 60_000;
+
+fn check_debugpy(config: &RuntimeLaunchConfig) -> Result<(), String> {
+    let mut probe = Command::new(&config.python_bin);
+    probe.current_dir(&config.backend_dir).arg("-c").arg("import debugpy");
+    match run_command_with_timeout(&mut probe, Duration::from_secs(10)) {
+        Ok((0, _)) => Ok(()),
+        Ok(_) => Err(format!(
+            "backend_debug needs debugpy for {} (pip install debugpy)",
+            config.python_bin
+        )),
+        Err(err) => Err(format!("backend_debug: cannot check for debugpy: {err}")),
+    }
+}
+
+fn service_ready_timeout(config: &RuntimeLaunchConfig, service: &str, default: Duration) -> Duration {
+    match config.backend_debug.as_ref() {
+        Some(debug) if service == "backend" => debug.ready_timeout,
+        _ => default,
+    }
+}
+
+fn is_env_var_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(first) if first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// Collects the start inputs on top of the defaults above; `build()` runs the checks that need
+// the whole picture (existing dirs, distinct ports, resolvable binaries, complete mongo_auth).
+struct RuntimeLaunchConfigBuilder {
+    config: RuntimeLaunchConfig,
+    local_backend_scheme: Option<String>,
+    remote_backend_url: Option<String>,
+    mongo_auth: Option<MongoAuthProfile>,
+    backend_url_env_name: Option<String>,
+    web_profile_arg: Option<String>,
+    backend_debug: Option<BackendDebugProfile>,
+    backend_debug_requested: Option<bool>,
+    backend_replicas: u32,
+    observe_only: bool,
+}
+
+impl RuntimeLaunchConfigBuilder {
+    fn new(mode: RuntimeMode, workspace_root: &Path, runtime_dir: &Path) -> Self {
+        Self {
+            config: RuntimeLaunchConfig {
+                mode,
+                web_port: DEFAULT_WEB_PORT,
+                backend_port: DEFAULT_BACKEND_PORT,
+                mongo_port: DEFAULT_MONGO_PORT,
+                backend_url: String::new(),
+                desktop_session_id: String::new(),
+                runtime_profile_path: None,
+                web_dev: false,
+                mongo_bin: None,
+                python_bin: DEFAULT_PYTHON_BIN.to_string(),
+                web_dir: workspace_root.join("web"),
+                backend_dir: workspace_root.join("backend"),
+                data_dir: None,
+                mongo_dbpath: None,
+                env: BTreeMap::new(),
+                backend_env: BTreeMap::new(),
+                web_env: BTreeMap::new(),
+                mongo_env: BTreeMap::new(),
+                graceful_restart: false,
+                log_dir: runtime_dir.join("logs"),
+                watch_paths: Vec::new(),
+                restart_policies: RestartPolicies::from_profile(None),
+                mongo_auth: None,
+                health_check_interval_ms: None,
+                max_unhealthy_before_restart: DEFAULT_MAX_UNHEALTHY,
+                autorestart_cooldown_ms: None,
+                web_stop_grace_ms: None,
+                backend_stop_grace_ms: None,
+                mongo_stop_grace_ms: None,
+                stop_total_timeout_ms: DEFAULT_STOP_TOTAL_TIMEOUT_MS,
+                readiness_consecutive_ok: 1,
+                max_total_log_bytes: None,
+                local_backend_scheme: "http",
+                web_disable_tls_verification: false,
+                adopt_existing_mongo: false,
+                mongo_lock_recovery: MongoLockRecovery::Off,
+                mongo_uri_env_name: DEFAULT_MONGO_URI_ENV.to_string(),
+                mongo_default_db: None,
+                stop_adopted: false,
+                backend_log_path: DEFAULT_BACKEND_LOG_PATH.to_string(),
+                backend_drain_path: None,
+                backend_config_path: None,
+                backend_url_env_names: vec![DEFAULT_BACKEND_URL_ENV.to_string()],
+                web_profile_arg: Some(DEFAULT_WEB_PROFILE_ARG.to_string()),
+                backend_debug: None,
+                backend_replica_ports: Vec::new(),
+                tasks: BTreeMap::new(),
+                on_ready: None,
+                correlation_id: None,
+            },
+            local_backend_scheme: None,
+            remote_backend_url: None,
+            mongo_auth: None,
+            backend_url_env_name: None,
+            web_profile_arg: None,
+            backend_debug: None,
+            backend_debug_requested: None,
+            backend_replicas: 1,
+            observe_only: false,
+        }
+    }
+
+    // The start request's flag, overriding the profile's `backend_debug.enabled`.
+    fn backend_debug(mut self, requested: Option<bool>) -> Self {
+        self.backend_debug_requested = requested;
+        self
+    }
+
+    // Nothing is spawned when observing, so binaries and watch_paths don't apply.
+    fn observe_only(mut self, observe_only: bool) -> Self {
+        self.observe_only = observe_only;
+        self
+    }
+
+    fn ports(mut self, web: u16, backend: u16, mongo: u16) -> Self {
+        self.config.web_port = web;
+        self.config.backend_port = backend;
+        self.config.mongo_port = mongo;
+        self
+    }
+
+    fn session(mut self, desktop_session_id: String, correlation_id: Option<String>) -> Self {
+        self.config.desktop_session_id = desktop_session_id;
+        self.config.correlation_id = correlation_id;
+        self
+    }
+
+    fn runtime_profile_path(mut self, path: &str) -> Self {
+        self.config.runtime_profile_path = Some(path.to_string()).filter(|value| !value.trim().is_empty());
+        self
+    }
+
+    fn web_dev(mut self, web_dev: bool) -> Self {
+        self.config.web_dev = web_dev;
+        self
+    }
+
+    // Blank values fall back to the default python and to "no managed Mongo".
+    fn binaries(mut self, python_bin: Option<String>, mongo_bin: Option<String>) -> Self {
+        let non_blank = |value: Option<String>| value.map(|raw| raw.trim().to_string()).filter(|raw| !raw.is_empty());
+        self.config.python_bin = non_blank(python_bin).unwrap_or_else(|| DEFAULT_PYTHON_BIN.to_string());
+        self.config.mongo_bin = non_blank(mongo_bin);
+        self
+    }
+
+    fn profile(mut self, profile: &RuntimeProfile) -> Self {
+        let non_blank = |value: &Option<String>| value.clone().filter(|raw| !raw.trim().is_empty());
+        let config = &mut self.config;
+        config.data_dir = profile.data_dir.clone();
+        config.mongo_dbpath = non_blank(&profile.mongo_dbpath).map(|raw| expand_user_path(&raw));
+        config.env = profile.env.clone().unwrap_or_default();
+        config.backend_env = profile.backend_env.clone().unwrap_or_default();
+        config.web_env = profile.web_env.clone().unwrap_or_default();
+        config.mongo_env = profile.mongo_env.clone().unwrap_or_default();
+        config.graceful_restart = profile.graceful_restart.unwrap_or(false);
+        config.restart_policies = RestartPolicies::from_profile(profile.restart_policy.as_ref());
+        config.health_check_interval_ms = profile.health_check_interval_ms.filter(|interval| *interval > 0);
+        config.max_unhealthy_before_restart = profile
+            .max_unhealthy_before_restart
+            .unwrap_or(DEFAULT_MAX_UNHEALTHY)
+            .max(1);
+        config.autorestart_cooldown_ms = profile.autorestart_cooldown_ms.filter(|cooldown| *cooldown > 0);
+        config.web_stop_grace_ms = profile.web_stop_grace_ms.filter(|grace| *grace > 0);
+        config.backend_stop_grace_ms = profile.backend_stop_grace_ms.filter(|grace| *grace > 0);
+        config.mongo_stop_grace_ms = profile.mongo_stop_grace_ms.filter(|grace| *grace > 0);
+        config.stop_total_timeout_ms = profile
+            .stop_total_timeout_ms
+            .filter(|timeout| *timeout > 0)
+            .unwrap_or(DEFAULT_STOP_TOTAL_TIMEOUT_MS);
+        config.readiness_consecutive_ok = profile
+            .readiness_consecutive_ok
+            .unwrap_or(1)
+            .clamp(1, READINESS_STREAK_CAP);
+        config.max_total_log_bytes = profile.max_total_log_bytes.filter(|budget| *budget > 0);
+        config.web_disable_tls_verification = profile.web_disable_tls_verification.unwrap_or(false);
+        config.adopt_existing_mongo = profile.adopt_existing_mongo.unwrap_or(false);
+        config.mongo_lock_recovery = profile
+            .mongo_recover_lock
+            .as_deref()
+            .map(MongoLockRecovery::from_raw)
+            .unwrap_or(MongoLockRecovery::Off);
+        config.stop_adopted = profile.stop_adopted.unwrap_or(false);
+        config.mongo_uri_env_name =
+            non_blank(&profile.mongo_uri_env_name).unwrap_or_else(|| DEFAULT_MONGO_URI_ENV.to_string());
+        config.mongo_default_db = non_blank(&profile.mongo_default_db);
+        config.backend_log_path =
+            non_blank(&profile.backend_log_path).unwrap_or_else(|| DEFAULT_BACKEND_LOG_PATH.to_string());
+        config.backend_drain_path = non_blank(&profile.backend_drain_path);
+        config.backend_config_path = non_blank(&profile.backend_config_path);
+        config.tasks = profile.tasks.clone().unwrap_or_default();
+        config.on_ready = non_blank(&profile.on_ready_command).map(|program| OnReadyHook {
+            program: program.trim().to_string(),
+            args: profile.on_ready_args.clone().unwrap_or_default(),
+            timeout: Duration::from_millis(profile.on_ready_timeout_ms.unwrap_or(DEFAULT_TASK_TIMEOUT_MS).max(1)),
+            after_recovery: profile.on_ready_after_recovery.unwrap_or(false),
+        });
+        self.backend_url_env_name = profile.backend_url_env_name.clone();
+        self.web_profile_arg = profile.web_profile_arg.clone();
+        self.backend_debug = profile.backend_debug.clone();
+        self.backend_replicas = profile.backend_replicas.unwrap_or(1);
+        if config.mode == RuntimeMode::LocalFullstack {
+            config.watch_paths = profile
+                .watch_paths
+                .iter()
+                .flatten()
+                .filter(|raw| !raw.trim().is_empty())
+                .map(|raw| {
+                    let path = expand_user_path(raw);
+                    if path.is_relative() {
+                        config.backend_dir.join(path)
+                    } else {
+                        path
+                    }
+                })
+                .collect();
+        }
+        self.local_backend_scheme = profile.local_backend_scheme.clone();
+        self.remote_backend_url = profile.backend_url.clone();
+        self.mongo_auth = profile.mongo_auth.clone();
+        self
+    }
+
+    fn build(self) -> Result<RuntimeLaunchConfig, String> {
+        let mut config = self.config;
+        if !config.web_dir.exists() || !config.backend_dir.exists() {
+            return Err(format!(
+                "workspace root not valid: web={} backend={}",
+                config.web_dir.display(),
+                config.backend_dir.display()
+            ));
+        }
+        config.local_backend_scheme = match self.local_backend_scheme.as_deref().map(str::trim) {
+            None | Some("") | Some("http") => "http",
+            Some("https") => "https",
+            Some(other) => return Err(format!("unsupported local_backend_scheme {other:?} (expected http or https)")),
+        };
+        config.backend_url = if config.mode == RuntimeMode::RemoteSlim {
+            let raw = self
+                .remote_backend_url
+                .filter(|value| !value.trim().is_empty())
+                .unwrap_or_else(|| DEFAULT_REMOTE_BACKEND_URL.to_string());
+            normalize_backend_url(&raw).map_err(|err| format!("invalid backend_url: {err}"))?
+        } else {
+            local_backend_url(config.local_backend_scheme, config.backend_port)
+        };
+        if !is_env_var_name(&config.mongo_uri_env_name) {
+            return Err(format!(
+                "mongo_uri_env_name {:?} is not a valid environment variable name",
+                config.mongo_uri_env_name
+            ));
+        }
+        if let Some(db) = config.mongo_default_db.as_deref() {
+            if db.len() > 63 || db.contains(['/', '\\', '.', ' ', '"', '$', '*', '<', '>', ':', '|', '?']) {
+                return Err(format!("mongo_default_db {db:?} is not a valid MongoDB database name"));
+            }
+        }
+        // Comma-separated, so one URL can be exported as e.g. "BACKEND_BASE_URL,NEXT_PUBLIC_API_URL".
+        if let Some(raw) = self.backend_url_env_name.as_deref() {
+            let names: Vec<String> = raw
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(str::to_string)
+                .collect();
+            if let Some(bad) = names.iter().find(|name| !is_env_var_name(name)) {
+                return Err(format!("backend_url_env_name {bad:?} is not a valid environment variable name"));
+            }
+            if !names.is_empty() {
+                config.backend_url_env_names = names;
+            }
+        }
+        // An empty web_profile_arg turns the pass-through off; RUNTIME_PROFILE_PATH is still set.
+        if let Some(raw) = self.web_profile_arg.as_deref().map(str::trim) {
+            if !raw.is_empty() && !raw.starts_with('-') {
+                return Err(format!("web_profile_arg {raw:?} must be a flag such as --config"));
+            }
+            config.web_profile_arg = Some(raw.to_string()).filter(|arg| !arg.is_empty());
+        }
+        if let Some(auth) = self.mongo_auth {
+            let username = auth.username.unwrap_or_default().trim().to_string();
+            let password = auth.password.unwrap_or_default();
+            if username.is_empty() || password.is_empty() {
+                return Err("mongo_auth requires both username and password".to_string());
+            }
+            config.mongo_auth = Some(MongoAuthConfig {
+                username,
+                password,
+                mongosh_bin: auth
+                    .mongosh_bin
+                    .filter(|value| !value.trim().is_empty())
+                    .unwrap_or_else(|| DEFAULT_MONGOSH_BIN.to_string()),
+            });
+        }
+        // A `backend_debug` section in the profile turns debugging on unless it says `enabled: false`.
+        let debug_configured = self.backend_debug.is_some();
+        let debug_profile = self.backend_debug.unwrap_or_default();
+        let debug_enabled = self
+            .backend_debug_requested
+            .or(debug_profile.enabled)
+            .unwrap_or(debug_configured);
+        if debug_enabled && is_backend_required(&config) {
+            let port = match debug_profile.port.unwrap_or(DEFAULT_BACKEND_DEBUG_PORT) {
+                0 => pick_free_port()?,
+                port => port,
+            };
+            config.backend_debug = Some(BackendDebugConfig {
+                port,
+                wait_for_client: debug_profile.wait_for_client.unwrap_or(true),
+                ready_timeout: Duration::from_millis(
+                    debug_profile
+                        .ready_timeout_ms
+                        .filter(|ms| *ms > 0)
+                        .unwrap_or(DEFAULT_BACKEND_DEBUG_READY_TIMEOUT_MS),
+                ),
+            });
+        }
+        if self.backend_replicas > 1 && is_backend_required(&config) {
+            if self.backend_replicas > MAX_BACKEND_REPLICAS {
+                return Err(format!("backend_replicas must be at most {MAX_BACKEND_REPLICAS}"));
+            }
+            if config.backend_debug.is_some() {
+                return Err("backend_debug cannot be combined with backend_replicas > 1".to_string());
+            }
+            config.backend_replica_ports = (1..self.backend_replicas)
+                .map(|offset| {
+                    u16::try_from(offset)
+                        .ok()
+                        .and_then(|offset| config.backend_port.checked_add(offset))
+                        .ok_or_else(|| format!("backend_replicas: port {} + {offset} is out of range", config.backend_port))
+                })
+                .collect::<Result<_, String>>()?;
+        }
+        // A graceful backend restart runs a second backend next to the first: it cannot bind the
+        // same debug port, and replicas keep their consecutive ports.
+        if config.graceful_restart && is_backend_required(&config) {
+            if config.backend_debug.is_some() {
+                return Err("graceful_restart cannot be combined with backend_debug".to_string());
+            }
+            if !config.backend_replica_ports.is_empty() {
+                return Err("graceful_restart cannot be combined with backend_replicas > 1".to_string());
+            }
+        }
+        let mut used: Vec<(&str, u16)> = vec![("web", config.web_port)];
+        if is_backend_required(&config) {
+            used.push(("backend", config.backend_port));
+        }
+        for (service, port) in BACKEND_REPLICA_SERVICES.iter().zip(&config.backend_replica_ports) {
+            used.push((service, *port));
+        }
+        if let Some(debug) = config.backend_debug.as_ref() {
+            used.push(("backend_debug", debug.port));
+        }
+        if is_mongo_required(&config) {
+            used.push(("mongo", config.mongo_port));
+        }
+        for (index, (service, port)) in used.iter().enumerate() {
+            if let Some((other, _)) = used[..index].iter().find(|(_, other_port)| other_port == port) {
+                return Err(format!("local_ports conflict: {other} and {service} both use port {port}"));
+            }
+        }
+        if self.observe_only {
+            config.watch_paths.clear();
+            return Ok(config);
+        }
+        if let Some(db_dir) = config.mongo_dbpath.as_ref().filter(|_| is_mongo_required(&config)) {
+            ensure_dir_writable(db_dir).map_err(|err| format!("mongo_dbpath: {err}"))?;
+        }
+        let missing_binaries = check_sidecar_binaries(&config);
+        if !missing_binaries.is_empty() {
+            return Err(missing_binaries.join(" | "));
+        }
+        if config.backend_debug.is_some() {
+            check_debugpy(&config)?;
+        }
+        Ok(config)
+    }
+}
+
+const WEB_DEV_PORT: u16 = 3000;
+
+// Catches web/mode combinations that would silently launch the wrong thing. Returns
+// (warnings, errors); errors abort a real start.
+fn check_launch_coherence(config: &RuntimeLaunchConfig, profile: &RuntimeProfile) -> (Vec<String>, Vec<String>) {
+    let mut warnings: Vec<String> = Vec::new();
+    let mut errors: Vec<String> = Vec::new();
+    if config.web_dev {
+        if config.web_port != WEB_DEV_PORT {
+            warnings.push(format!(
+                "web_dev runs `next dev -p {WEB_DEV_PORT}`, so local_ports.web={} is ignored",
+                config.web_port
+            ));
+        }
+    } else {
+        let server_js = config.web_dir.join(".next").join("standalone").join("server.js");
+        if !server_js.exists() {
+            errors.push(format!(
+                "standalone web build missing at {}; run `npm run build` in web/ or start with web_dev",
+                server_js.display()
+            ));
+        }
+    }
+    let expected_node_env = if config.web_dev { "development" } else { "production" };
+    if let Some(node_env) = config.web_env.get("NODE_ENV").or_else(|| config.env.get("NODE_ENV")) {
+        if node_env != expected_node_env {
+            warnings.push(format!(
+                "NODE_ENV={node_env} overrides the {expected_node_env} default for the {} web server",
+                if config.web_dev { "dev" } else { "standalone" }
+            ));
+        }
+    }
+    if profile.tls_insecure == Some(true) {
+        warnings.push(
+            "tls_insecure only concerns the shell's own probes, which never verify certificates; it no longer \
+             changes the web sidecar (see web_disable_tls_verification)"
+                .to_string(),
+        );
+    }
+    if config.web_disable_tls_verification {
+        warnings.push(
+            "web_disable_tls_verification: the web server runs with NODE_TLS_REJECT_UNAUTHORIZED=0, so none of its \
+             outbound HTTPS calls verify certificates"
+                .to_string(),
+        );
+    }
+    if config.mode == RuntimeMode::RemoteSlim {
+        if profile.backend_url.as_deref().map(str::trim).unwrap_or("").is_empty() {
+            warnings.push("remote_slim without backend_url falls back to http://127.0.0.1:8080".to_string());
+        }
+        let local_only = [
+            ("mongo_auth", profile.mongo_auth.is_some()),
+            ("mongo_bin", config.mongo_bin.is_some()),
+            ("backend_env", !config.backend_env.is_empty()),
+            ("mongo_env", !config.mongo_env.is_empty()),
+            ("local_backend_scheme", profile.local_backend_scheme.is_some()),
+            ("mongo_uri_env_name", profile.mongo_uri_env_name.is_some()),
+            ("mongo_default_db", profile.mongo_default_db.is_some()),
+            ("adopt_existing_mongo", config.adopt_existing_mongo),
+        ];
+        for (option, set) in local_only {
+            if set {
+                warnings.push(format!("{option} is ignored in remote_slim mode"));
+            }
+        }
+    }
+    (warnings, errors)
+}
+
+// Returns the problems found; the caller decides whether they warn or abort the start.
+fn check_min_versions(config: &RuntimeLaunchConfig, profile: &RuntimeProfile) -> Vec<String> {
+    let mut checks: Vec<(&str, String, &str)> = Vec::new();
+    if let Some(min) = profile.min_node_version.as_deref().filter(|v| !v.trim().is_empty()) {
+        checks.push(("node", node_bin().to_string(), min));
+    }
+    if is_backend_required(config) {
+        if let Some(min) = profile.min_python_version.as_deref().filter(|v| !v.trim().is_empty()) {
+            checks.push(("python", config.python_bin.clone(), min));
+        }
+    }
+    let mut problems: Vec<String> = Vec::new();
+    for (name, program, min) in checks {
+        let Some(minimum) = parse_version(min) else {
+            problems.push(format!("min_{name}_version {min:?} is not a valid version"));
+            continue;
+        };
+        let reported = match query_tool_version(&program) {
+            Ok(text) => text,
+            Err(err) => {
+                problems.push(err);
+                continue;
+            }
+        };
+        match parse_version(&reported) {
+            Some(actual) if version_at_least(&actual, &minimum) => {}
+            Some(_) => problems.push(format!("{name} {reported} is older than required {min}")),
+            None => problems.push(format!("could not parse {name} version from {reported:?}")),
+        }
+    }
+    problems
+}
+
+// Randomized `base ± spread` so sidecars recovering together don't poll in lockstep.
+// RandomState is seeded per instance, which is plenty for jitter without a rand crate.
+fn jittered(base: Duration, spread: Duration) -> Duration {
+    let spread_ms = spread.as_millis() as u64;
+    if spread_ms == 0 {
+        return base;
+    }
+    let roll = RandomState::new().build_hasher().finish() % (2 * spread_ms + 1);
+    (base + Duration::from_millis(roll)).saturating_sub(spread)
+}
+
+const READY_POLL_INTERVAL: Duration = Duration::from_millis(150);
+const READY_POLL_JITTER: Duration = Duration::from_millis(50);
+const READINESS_STREAK_CAP: u32 = 20;
+
+// `readiness_consecutive_ok`: a sidecar that accepts a connection and then dies during init
+// must not count as ready, so readiness needs that many successful probes in a row.
+struct ReadyStreak {
+    needed: u32,
+    streak: u32,
+    probes: u32,
+}
+
+impl ReadyStreak {
+    fn new(needed: u32) -> Self {
+        Self { needed: needed.max(1), streak: 0, probes: 0 }
+    }
+
+    fn observe(&mut self, ok: bool) -> bool {
+        self.probes = self.probes.saturating_add(1);
+        self.streak = if ok { self.streak + 1 } else { 0 };
+        self.streak >= self.needed
+    }
+
+    // Enough time for a full streak, for waits that are left with (almost) none.
+    fn min_window(&self) -> Duration {
+        (READY_POLL_INTERVAL + READY_POLL_JITTER) * self.needed
+    }
+}
+
+// Some(probe count) once `needed` probes in a row connected. Gives up early once `exited`
+// reports the process gone; the caller tells that apart from a timeout by asking it again.
+fn wait_for_port(port: u16, timeout: Duration, needed: u32, exited: &mut dyn FnMut() -> bool) -> Option<u32> {
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    let mut probe = || TcpStream::connect_timeout(&addr, Duration::from_millis(300)).is_ok();
+    wait_for_probe(&mut probe, timeout, needed, exited)
+}
+
+// The same wait for mongod answering `isMaster`, for restarts; the start uses `wait_for_mongo`.
+fn wait_for_mongo_ping(port: u16, timeout: Duration, needed: u32, exited: &mut dyn FnMut() -> bool) -> Option<u32> {
+    let mut probe = || mongo_ping(port, Duration::from_millis(500)).is_ok();
+    wait_for_probe(&mut probe, timeout, needed, exited)
+}
+
+fn wait_for_probe(
+    probe: &mut dyn FnMut() -> bool,
+    timeout: Duration,
+    needed: u32,
+    exited: &mut dyn FnMut() -> bool,
+) -> Option<u32> {
+    let deadline = Instant::now() + timeout;
+    let mut ready = ReadyStreak::new(needed);
+    while Instant::now() < deadline && !shutdown_requested() {
+        if ready.observe(probe()) {
+            return Some(ready.probes);
+        }
+        if exited() {
+            return None;
+        }
+        std::thread::sleep(jittered(READY_POLL_INTERVAL, READY_POLL_JITTER));
+    }
+    None
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct HttpTarget {
+    scheme: String,
+    host: String,
+    port: u16,
+    path: String,
+}
+
+fn parse_http_url(raw: &str) -> Result<HttpTarget, String> {
+    let text = raw.trim();
+    let (scheme, rest) = text
+        .split_once("://")
+        .ok_or_else(|| format!("url {text:?} has no scheme"))?;
+    let scheme = scheme.to_ascii_lowercase();
+    let default_port = match scheme.as_str() {
+        "http" => 80,
+        "https" => 443,
+        other => return Err(format!("unsupported url scheme {other:?}")),
+    };
+    let (authority, path) = match rest.find('/') {
+        Some(index) => (&rest[..index], &rest[index..]),
+        None => (rest, "/"),
+    };
+    // Bracketed IPv6 hosts (`[::1]:8080`) keep their colons.
+    let (host, port_text) = if let Some(bracketed) = authority.strip_prefix('[') {
+        let (host, after) = bracketed
+            .split_once(']')
+            .ok_or_else(|| format!("url {text:?} has an unterminated IPv6 host"))?;
+        (host, after.strip_prefix(':'))
+    } else {
+        match authority.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (authority, None),
+        }
+    };
+    let port = match port_text {
+        Some(value) => value
+            .parse::<u16>()
+            .map_err(|_| format!("url {text:?} has an invalid port"))?,
+        None => default_port,
+    };
+    if host.is_empty() {
+        return Err(format!("url {text:?} has no host"));
+    }
+    Ok(HttpTarget {
+        scheme,
+        host: host.to_string(),
+        port,
+        path: path.to_string(),
+    })
+}
+
+fn join_url_path(base: &str, path: &str) -> String {
+    format!("{}/{}", base.trim_end_matches('/'), path.trim_start_matches('/'))
+}
+
+// Canonical form for a configured backend URL: `scheme://host:port[/path]`, with http assumed
+// when the scheme is missing, the port always spelled out and no trailing slash, so
+// `127.0.0.1:8080`, `http://127.0.0.1:8080/` and `HTTP://127.0.0.1:8080` all come out the same.
+fn normalize_backend_url(raw: &str) -> Result<String, String> {
+    let text = raw.trim();
+    if text.chars().any(char::is_whitespace) {
+        return Err(format!("url {text:?} contains whitespace"));
+    }
+    let with_scheme = if text.contains("://") {
+        text.to_string()
+    } else {
+        format!("http://{text}")
+    };
+    let target = parse_http_url(&with_scheme)?;
+    if target.path.contains(['?', '#']) {
+        return Err(format!("url {text:?} has a query or fragment"));
+    }
+    let host = target.host.to_ascii_lowercase();
+    if host.contains(['@', '?', '#', '[', ']']) || (host.contains(':') && host.parse::<std::net::Ipv6Addr>().is_err()) {
+        return Err(format!("url {text:?} has an invalid host"));
+    }
+    if target.port == 0 {
+        return Err(format!("url {text:?} has port 0"));
+    }
+    let host = if host.contains(':') { format!("[{host}]") } else { host };
+    Ok(format!(
+        "{}://{host}:{}{}",
+        target.scheme,
+        target.port,
+        target.path.trim_end_matches('/')
+    ))
+}
+
+#[derive(Debug, Clone)]
+struct HttpResponse {
+    status: u16,
+    body: String,
+}
+
+fn decode_chunked(body: &[u8]) -> Vec<u8> {
+    let mut out: Vec<u8> = Vec::new();
+    let mut rest = body;
+    while let Some(line_end) = rest.windows(2).position(|w| w == b"\r\n") {
+        let size_text = String::from_utf8_lossy(&rest[..line_end]);
+        let size_hex = size_text.split(';').next().unwrap_or("").trim();
+        let Ok(size) = usize::from_str_radix(size_hex, 16) else {
+            break;
+        };
+        if size == 0 {
+            break;
+        }
+        let start = line_end + 2;
+        let end = (start + size).min(rest.len());
+        out.extend_from_slice(&rest[start..end]);
+        rest = rest.get(end + 2..).unwrap_or(&[]);
+    }
+    out
+}
+
+const HTTP_MAX_RESPONSE_BYTES: usize = 1024 * 1024;
+
+// Minimal HTTP/1.1 client for local health probes; the shell has no HTTP dependency.
+fn http_request(
+    method: &str,
+    host: &str,
+    port: u16,
+    path: &str,
+    body: Option<&str>,
+    timeout: Duration,
+) -> Result<HttpResponse, String> {
+    use std::net::ToSocketAddrs;
+    let addr = (host, port)
+        .to_socket_addrs()
+        .map_err(|err| format!("cannot resolve {host}:{port}: {err}"))?
+        .next()
+        .ok_or_else(|| format!("cannot resolve {host}:{port}"))?;
+    let mut stream = TcpStream::connect_timeout(&addr, timeout).map_err(|err| format!("connect {addr}: {err}"))?;
+    let _ = stream.set_read_timeout(Some(timeout));
+    let _ = stream.set_write_timeout(Some(timeout));
+    let mut request = format!(
+        "{method} {path} HTTP/1.1\r\nHost: {host}:{port}\r\nConnection: close\r\nAccept: */*\r\n"
+    );
+    if let Some(payload) = body {
+        request.push_str(&format!(
+            "Content-Type: application/json\r\nContent-Length: {}\r\n",
+            payload.len()
+        ));
+    }
+    request.push_str("\r\n");
+    if let Some(payload) = body {
+        request.push_str(payload);
+    }
+    stream
+        .write_all(request.as_bytes())
+        .map_err(|err| format!("write {addr}: {err}"))?;
+
+    let mut raw: Vec<u8> = Vec::new();
+    let mut buf = [0u8; 8192];
+    loop {
+        match stream.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => {
+                raw.extend_from_slice(&buf[..n]);
+                if raw.len() >= HTTP_MAX_RESPONSE_BYTES {
+                    break;
+                }
+            }
+            Err(err) if raw.is_empty() => return Err(format!("read {addr}: {err}")),
+            Err(_) => break,
+        }
+    }
+    let header_end = raw
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .ok_or_else(|| format!("malformed HTTP response from {addr}"))?;
+    let head = String::from_utf8_lossy(&raw[..header_end]).to_string();
+    let status = head
+        .lines()
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or_else(|| format!("malformed HTTP status line from {addr}"))?;
+    let chunked = head.lines().any(|line| {
+        let lower = line.to_ascii_lowercase();
+        lower.starts_with("transfer-encoding:") && lower.contains("chunked")
+    });
+    let payload = &raw[header_end + 4..];
+    let body = if chunked {
+        String::from_utf8_lossy(&decode_chunked(payload)).to_string()
+    } else {
+        String::from_utf8_lossy(payload).to_string()
+    };
+    Ok(HttpResponse { status, body })
+}
+
+// The shell has no TLS client, so backend commands that need an HTTP answer (not just a TCP
+// check) refuse an https backend_url up front instead of failing somewhere in the transport.
+fn https_unsupported(url: &str, what: &str) -> Option<String> {
+    url.trim_start()
+        .to_ascii_lowercase()
+        .starts_with("https://")
+        .then(|| format!("{what} is unsupported for an https backend_url ({url}); the shell has no TLS client"))
+}
+
+fn http_get(url: &str, timeout: Duration) -> Result<HttpResponse, String> {
+    let target = parse_http_url(url)?;
+    if target.scheme == "https" {
+        return Err("https is not supported by the shell's HTTP probe".to_string());
+    }
+    http_request("GET", &target.host, target.port, &target.path, None, timeout)
+}
+
+fn http_post(url: &str, body: &str, timeout: Duration) -> Result<HttpResponse, String> {
+    let target = parse_http_url(url)?;
+    if target.scheme == "https" {
+        return Err("https is not supported by the shell's HTTP probe".to_string());
+    }
+    http_request("POST", &target.host, target.port, &target.path, Some(body), timeout)
+}
+
+fn log_len(path: &Path) -> u64 {
+    fs::metadata(path).map(|meta| meta.len()).unwrap_or(0)
+}
+
+// The servers' own "listening" lines: Next.js `- Local: http://...`, `ready on http://...` and
+// `started server on ..., url: http://...`, uvicorn `Uvicorn running on http://...`.
+fn is_listening_line(line: &str) -> bool {
+    let lower = line.to_ascii_lowercase();
+    ["local:", "ready on ", "started server on ", "running on http"]
+        .iter()
+        .any(|marker| lower.contains(marker))
+}
+
+// Picks the last `scheme://host:PORT` on a listening line. Other URLs in the log (a
+// `mongodb://` URI, an LLM endpoint) are not the service's own port.
+fn parse_logged_port(text: &str) -> Option<u16> {
+    text.lines().rev().filter(|line| is_listening_line(line)).find_map(parse_url_port)
+}
+
+fn parse_url_port(text: &str) -> Option<u16> {
+    let mut found = None;
+    let mut rest = text;
+    while let Some(pos) = rest.find("://") {
+        let after = &rest[pos + 3..];
+        let authority_end = after
+            .find(|c: char| c == '/' || c.is_whitespace())
+            .unwrap_or(after.len());
+        if let Some((_, port)) = after[..authority_end].rsplit_once(':') {
+            let digits: String = port.chars().take_while(|c| c.is_ascii_digit()).collect();
+            if let Ok(value) = digits.parse::<u16>() {
+                if value != 0 {
+                    found = Some(value);
+                }
+            }
+        }
+        rest = &after[authority_end..];
+    }
+    found
+}
+
+// Stops waiting as soon as mongod exits; a bad dbpath or version mismatch kills it immediately.
+// Ready means answering `isMaster`, not just an open port. Ok(probe count); see `ReadyStreak`.
+fn wait_for_mongo(state: &mut RuntimeProcessState, port: u16, timeout: Duration, needed: u32) -> Result<u32, String> {
+    let deadline = Instant::now() + timeout;
+    let mut ready = ReadyStreak::new(needed);
+    while Instant::now() < deadline {
+        if shutdown_requested() {
+            return Err(SHUTDOWN_INTERRUPTED.to_string());
+        }
+        if ready.observe(mongo_ping(port, Duration::from_millis(500)).is_ok()) {
+            return Ok(ready.probes);
+        }
+        if let Some(Ok(Some(status))) = state.mongo.as_mut().map(|child| child.try_wait()) {
+            return Err(startup_exit_reason("mongo", status));
+        }
+        std::thread::sleep(jittered(READY_POLL_INTERVAL, READY_POLL_JITTER));
+    }
+    Err(format!("mongo is not accepting connections on port {port} yet"))
+}
+
+const MONGO_FAILURE_LOG_LINES: usize = 8;
+const STARTUP_FAILURE_LOG_LINES: usize = 8;
+// Lets the log pump copy what the process wrote just before exiting.
+const STARTUP_EXIT_LOG_SETTLE: Duration = Duration::from_millis(150);
+
+fn startup_exit_reason(service: &str, status: std::process::ExitStatus) -> String {
+    let how = status.code().map_or_else(|| "killed by a signal".to_string(), |code| format!("code {code}"));
+    format!("{service} process exited during startup ({how})")
+}
+
+// Exit reason plus the last lines the service wrote since `log_offset`, where its stderr ends up.
+fn startup_exit_failure(
+    config: &RuntimeLaunchConfig,
+    service: &str,
+    status: std::process::ExitStatus,
+    log_offset: u64,
+) -> String {
+    std::thread::sleep(STARTUP_EXIT_LOG_SETTLE);
+    let tail = service_log_tail(config, service, log_offset, STARTUP_FAILURE_LOG_LINES);
+    let detail = if tail.is_empty() { String::new() } else { format!(": {}", tail.join(" | ")) };
+    format!("{}{detail}", startup_exit_reason(service, status))
+}
+
+fn service_log_tail(config: &RuntimeLaunchConfig, service: &str, log_offset: u64, lines: usize) -> Vec<String> {
+    let path = service_log_path(&config.log_dir, service);
+    let Ok((_, _, data)) = read_log_chunk(&path, log_offset, 256 * 1024) else {
+        return Vec::new();
+    };
+    let text = String::from_utf8_lossy(&data);
+    let kept: Vec<String> = text.lines().map(str::trim).filter(|line| !line.is_empty()).map(String::from).collect();
+    kept[kept.len().saturating_sub(lines)..].to_vec()
+}
+const MONGO_REPAIR_TIMEOUT: Duration = Duration::from_secs(600);
+
+// mongod's messages when the dbpath lock is held or left behind by an unclean shutdown.
+fn is_mongo_lock_failure(log_tail: &[String]) -> bool {
+    log_tail.iter().any(|line| {
+        line.contains("mongod.lock") || line.contains("DBPathInUse") || line.contains("Unable to lock")
+    })
+}
+
+// PIDs holding `path` open. On Windows a live mongod keeps its lock file exclusively open, so
+// the removal itself fails instead.
+fn lock_file_holders(path: &Path) -> Result<Vec<u32>, String> {
+    if cfg!(windows) {
+        return Ok(Vec::new());
+    }
+    let (_, output) = run_command_with_timeout(
+        Command::new("lsof").arg("-t").arg(path),
+        LISTENER_LOOKUP_TIMEOUT,
+    )?;
+    Ok(output.lines().filter_map(|line| line.trim().parse().ok()).collect())
+}
+
+// Clears a stale mongod.lock as configured by `mongo_recover_lock`, refusing when a live
+// process still holds it. Returns what was done, for the audit event.
+fn recover_mongo_lock(launch: &RuntimeLaunchConfig) -> Result<String, String> {
+    let db_dir = mongo_dbpath(launch).ok_or("no dbpath known (data_dir unset), lock left alone")?;
+    let lock_path = db_dir.join("mongod.lock");
+    match lock_file_holders(&lock_path) {
+        Ok(holders) if holders.is_empty() => {}
+        Ok(holders) => {
+            return Err(format!(
+                "{} is held by running pid {holders:?}; not a stale lock",
+                lock_path.display()
+            ))
+        }
+        Err(err) => return Err(format!("cannot verify that {} is stale: {err}", lock_path.display())),
+    }
+    match launch.mongo_lock_recovery {
+        MongoLockRecovery::Off => Err("mongo_recover_lock is off".to_string()),
+        MongoLockRecovery::RemoveLock => {
+            fs::remove_file(&lock_path).map_err(|err| format!("cannot remove {}: {err}", lock_path.display()))?;
+            Ok(format!("removed stale {}", lock_path.display()))
+        }
+        MongoLockRecovery::Repair => {
+            let mongo_bin = launch.mongo_bin.as_deref().ok_or("no mongod binary configured")?;
+            let mut repair = Command::new(mongo_bin);
+            repair.arg("--dbpath").arg(&db_dir).arg("--repair");
+            match run_command_with_timeout(&mut repair, MONGO_REPAIR_TIMEOUT)? {
+                (0, _) => Ok(format!("ran mongod --repair on {}", db_dir.display())),
+                (code, output) => Err(format!(
+                    "mongod --repair exited with {code}: {}",
+                    output.lines().last().unwrap_or_default()
+                )),
+            }
+        }
+    }
+}
+
+// Last lines mongod wrote since `log_offset`. Structured (4.4+) log lines are shortened to
+// their `msg` plus `attr.error`, which is where the reason for a failed start ends up.
+fn mongo_log_tail(config: &RuntimeLaunchConfig, log_offset: u64) -> Vec<String> {
+    // Read in full: the shortened lines are what the last MONGO_FAILURE_LOG_LINES are taken from.
+    let lines: Vec<String> = service_log_tail(config, "mongo", log_offset, usize::MAX)
+        .into_iter()
+        .map(|line| match serde_json::from_str::<serde_json::Value>(&line) {
+            Ok(entry) if entry.get("msg").is_some() => {
+                let msg = entry.get("msg").and_then(|value| value.as_str()).unwrap_or_default();
+                match entry.pointer("/attr/error") {
+                    Some(serde_json::Value::String(error)) => format!("{msg}: {error}"),
+                    Some(error) => format!("{msg}: {error}"),
+                    None => msg.to_string(),
+                }
+            }
+            _ => line,
+        })
+        .collect();
+    lines[lines.len().saturating_sub(MONGO_FAILURE_LOG_LINES)..].to_vec()
+}
+
+// Exit status of a child that has exited; std keeps it, so asking again returns it again.
+fn exit_status(child: Option<&mut Child>) -> Option<std::process::ExitStatus> {
+    child.and_then(|child| child.try_wait().ok().flatten())
+}
+
+// Readiness after a restart: the service port, or for mongo an `isMaster` answer. The new
+// process sits in its slot during the wait, so the slot is polled (briefly locking the state)
+// and an exit fails the wait at once, with its log tail since `log_offset`. An empty slot
+// means the watchdog reaped it first, or a stop took it.
+fn wait_for_restarted_service(
+    shared: &Mutex<RuntimeProcessState>,
+    config: &RuntimeLaunchConfig,
+    service: &str,
+    log_offset: u64,
+) -> Result<(), String> {
+    let timeout = service_ready_timeout(config, service, Duration::from_secs(30));
+    let needed = config.readiness_consecutive_ok;
+    let gone = || {
+        let mut state = lock_runtime(shared);
+        match service_slot(&mut state, service) {
+            Some(Some(child)) => exit_status(Some(child)).map(Ok),
+            Some(None) => Some(Err(state.last_exit.get(service).cloned().unwrap_or_default())),
+            None => None,
+        }
+    };
+    let mut exited = || gone().is_some();
+    let ready = match service {
+        "mongo" => wait_for_mongo_ping(config.mongo_port, timeout, needed, &mut exited).is_some(),
+        _ => service_ready_port(config, service)
+            .is_none_or(|port| wait_for_port(port, timeout, needed, &mut exited).is_some()),
+    };
+    if ready {
+        return Ok(());
+    }
+    Err(match gone() {
+        Some(Ok(status)) => startup_exit_failure(config, service, status, log_offset),
+        Some(Err(exit)) if !exit.is_empty() => format!("{exit} before it became ready after restart"),
+        Some(Err(_)) => format!("{service} was stopped before it became ready after restart"),
+        None => format!("{service} did not become ready after restart"),
+    })
+}
+
+fn port_accepts(port: u16) -> bool {
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    TcpStream::connect_timeout(&addr, Duration::from_millis(300)).is_ok()
+}
+
+// Like `wait_for_port`, but also watches the service log written since `log_offset` and
+// accepts a different port the service reports listening on (e.g. after a port conflict).
+// `port_busy`: something already answered on `port` before the service was spawned, so an
+// answer there proves nothing and only a port the service logged counts.
+fn wait_for_service_port(
+    config: &RuntimeLaunchConfig,
+    service: &str,
+    port: u16,
+    port_busy: bool,
+    log_offset: u64,
+    timeout: Duration,
+    mut child: Option<&mut Child>,
+) -> Result<(u16, u32), String> {
+    let deadline = Instant::now() + timeout;
+    let log_path = service_log_path(&config.log_dir, service);
+    let mut next_log_check = Instant::now() + Duration::from_secs(1);
+    let mut ready = ReadyStreak::new(config.readiness_consecutive_ok);
+    // Switches to the port the service logged once that one answers instead.
+    let mut probed = port;
+    while Instant::now() < deadline {
+        if shutdown_requested() {
+            return Err(SHUTDOWN_INTERRUPTED.to_string());
+        }
+        if (!port_busy || probed != port) && ready.observe(port_accepts(probed)) {
+            return Ok((probed, ready.probes));
+        }
+        // Reaps a sidecar that died during init right away instead of leaving it a zombie
+        // until the wait times out.
+        if let Some(Ok(Some(status))) = child.as_mut().map(|child| child.try_wait()) {
+            return Err(startup_exit_failure(config, service, status, log_offset));
+        }
+        if Instant::now() >= next_log_check {
+            next_log_check = Instant::now() + Duration::from_secs(1);
+            if let Ok((_, _, data)) = read_log_chunk(&log_path, log_offset, 64 * 1024) {
+                let logged = parse_logged_port(&String::from_utf8_lossy(&data));
+                if let Some(actual) = logged.filter(|actual| *actual != probed) {
+                    if port_accepts(actual) {
+                        probed = actual;
+                        ready.streak = 0;
+                        if ready.observe(true) {
+                            return Ok((probed, ready.probes));
+                        }
+                    }
+                }
+            }
+        }
+        std::thread::sleep(jittered(READY_POLL_INTERVAL, READY_POLL_JITTER));
+    }
+    if port_busy && probed == port {
+        return Err(format!(
+            "{service} port {port} was already in use before it started, and it reported listening nowhere else"
+        ));
+    }
+    Err(format!("{service} did not become ready on port {probed} in time"))
+}
+
+fn stop_child(child: &mut Option<Child>) {
+    if let Some(mut process) = child.take() {
+        let _ = process.kill();
+        let _ = process.wait();
+    }
+}
+
+const DEFAULT_STOP_TOTAL_TIMEOUT_MS: u64 = 30_000;
+
+fn stop_grace(config: &RuntimeLaunchConfig, service: &str) -> Option<u64> {
+    match service {
+        "web" => config.web_stop_grace_ms,
+        "mongo" => config.mongo_stop_grace_ms,
+        "backend" => config.backend_stop_grace_ms,
+        _ if backend_replica_index(service).is_some() => config.backend_stop_grace_ms,
+        _ => None,
+    }
+}
+
+// SIGTERM, then up to `grace` for the process to exit on its own. Returns false when it is
+// still running (or can't be signalled, as on Windows); the caller kills it then.
+fn terminate_child(child: &mut Child, grace: Duration) -> bool {
+    if !send_term(child) {
+        return false;
+    }
+    let deadline = Instant::now() + grace;
+    while Instant::now() < deadline {
+        if matches!(child.try_wait(), Ok(Some(_))) {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    false
+}
+
+#[cfg(unix)]
+fn send_term(child: &Child) -> bool {
+    send_signal(child.id(), libc::SIGTERM).is_ok()
+}
+
+// No SIGTERM on Windows; the caller kills the process instead.
+#[cfg(not(unix))]
+fn send_term(_child: &Child) -> bool {
+    false
+}
+
+// Planned stops go through here so each service gets its `<service>_stop_grace_ms`; a service
+// without one is killed outright, like `stop_child`.
+fn stop_service(state: &mut RuntimeProcessState, service: &str) {
+    stop_service_until(state, service, None);
+}
+
+// Like `stop_service`, with the grace period cut short at `deadline`. None when the service
+// had no process; the flag is set when the deadline is what got it killed.
+fn stop_service_until(
+    state: &mut RuntimeProcessState,
+    service: &str,
+    deadline: Option<Instant>,
+) -> Option<(DesktopRuntimeServiceStop, bool)> {
+    let grace_ms = state.launch_config.as_ref().and_then(|config| stop_grace(config, service));
+    let mut child = service_slot(state, service).and_then(Option::take)?;
+    let started = Instant::now();
+    let mut report = DesktopRuntimeServiceStop {
+        service: service.to_string(),
+        was_running: matches!(child.try_wait(), Ok(None)),
+        graceful: false,
+        forced: false,
+        exit_observed: false,
+        stop_ms: 0,
+    };
+    let mut cut_short = false;
+    if report.was_running {
+        let mut grace = Duration::from_millis(grace_ms.unwrap_or(0));
+        if let Some(left) = deadline.map(|deadline| deadline.saturating_duration_since(started)) {
+            if grace_ms.is_some() && left < grace {
+                cut_short = true;
+                grace = left;
+            }
+        }
+        report.graceful = !grace.is_zero() && terminate_child(&mut child, grace);
+        if !report.graceful {
+            let _ = child.kill();
+            report.forced = true;
+        }
+    }
+    report.exit_observed = child.wait().is_ok();
+    report.stop_ms = started.elapsed().as_millis() as u64;
+    if let Some(grace_ms) = grace_ms.filter(|_| report.forced && !cut_short) {
+        push_runtime_event_with(
+            state,
+            "warn",
+            service,
+            format!("{service} did not exit within {grace_ms}ms of SIGTERM; killed it"),
+            event_fields([("service", service.into()), ("grace_ms", grace_ms.into())]),
+        );
+    }
+    Some((report, cut_short))
+}
+
+fn push_runtime_event(state: &mut RuntimeProcessState, level: &str, source: &str, message: impl Into<String>) {
+    push_runtime_event_with(state, level, source, message, serde_json::Map::new());
+}
+
+fn event_fields<const N: usize>(pairs: [(&str, serde_json::Value); N]) -> serde_json::Map<String, serde_json::Value> {
+    let mut fields = serde_json::Map::new();
+    for (key, value) in pairs {
+        fields.insert(key.to_string(), value);
+    }
+    fields
+}
+
+// `PQA_LOG_STDERR=1` (or `0`) wins over the profile's `log_to_stderr`.
+fn env_flag(name: &str) -> Option<bool> {
+    match env::var(name).ok()?.trim().to_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+fn env_log_to_stderr() -> Option<bool> {
+    env_flag("PQA_LOG_STDERR")
+}
+
+// Read at startup too, so an ephemeral worker writes no event file even before its first start.
+fn env_persist_diagnostics() -> Option<bool> {
+    env_flag("PQA_PERSIST_DIAGNOSTICS")
+}
+
+// One line per event for journald/CI logs, which add their own timestamps:
+// `pqa-runtime 1718000000000 WARN watchdog: message {"service":"web"}`.
+fn format_stderr_event(event: &DesktopRuntimeDiagEvent) -> String {
+    let mut line = format!(
+        "pqa-runtime {} {} {}: {}",
+        event.ts_ms,
+        event.level.as_str().to_uppercase(),
+        event.source.as_str(),
+        event.message.replace('\n', " ")
+    );
+    if !event.fields.is_empty() {
+        if let Ok(fields) = serde_json::to_string(&event.fields) {
+            line.push(' ');
+            line.push_str(&fields);
+        }
+    }
+    line
+}
+
+fn push_runtime_event_with(
+    state: &mut RuntimeProcessState,
+    level: &str,
+    source: &str,
+    message: impl Into<String>,
+    fields: serde_json::Map<String, serde_json::Value>,
+) {
+    let level = EventLevel::from_raw(level);
+    // warn/error always survive the profile threshold.
+    if level.rank() < state.min_event_level.rank().min(EventLevel::Warn.rank()) {
+        return;
+    }
+    let source = EventSource::from_raw(source);
+    if !state.source_filter.allows(&source, level) {
+        return;
+    }
+    ensure_diagnostics_state(state, None);
+    let event = DesktopRuntimeDiagEvent {
+        ts_ms: now_ms(),
+        level,
+        source,
+        message: message.into(),
+        session_id: state.desktop_session_id.clone(),
+        correlation_id: state.correlation_id.clone(),
+        instance_label: state.instance_label.clone(),
+        fields,
+    };
+    if state.log_to_stderr {
+        eprintln!("{}", format_stderr_event(&event));
+    }
+    if let Some(stream) = state.start_stream.as_ref() {
+        let mut message = DesktopRuntimeStartEvent::new("log");
+        message.event = Some(event.clone());
+        (stream.0)(&message);
+    }
+    state.events.push(event);
+    persist_runtime_events(state);
+}
+
+fn clear_launch_state(state: &mut RuntimeProcessState) {
+    state.auto_restart = false;
+    state.parked_services.clear();
+    state.restarting_services.clear();
+    state.unhealthy_streaks.clear();
+    state.unhealthy_restart_count = 0;
+    state.last_health_check_ms = None;
+    state.last_exit.clear();
+    state.restart_count = 0;
+    state.last_restart_ms = None;
+    state.auto_restart_disabled_ms = None;
+    state.last_crash_ms = None;
+    state.failed_restart_ms.clear();
+    state.escalated_restart_ms = None;
+    state.observe_only = false;
+    state.last_log_budget_check_ms = None;
+    state.log_budget_exceeded = false;
+    state.profile_checked_mtime = None;
+    state.last_drift_check_ms = None;
+    state.config_drift_fields.clear();
+    state.observed_probes.clear();
+    state.last_observe_ms = None;
+    state.backend_health = None;
+    state.launched_commands.clear();
+    state.launch_config = None;
+}
+
+// The per-service grace periods run one after the other, so `stop_total_timeout_ms` caps
+// their sum; whatever is still running at that point is killed.
+fn stop_processes(state: &mut RuntimeProcessState) {
+    let total_ms = state
+        .launch_config
+        .as_ref()
+        .map(|config| config.stop_total_timeout_ms)
+        .unwrap_or(DEFAULT_STOP_TOTAL_TIMEOUT_MS);
+    let deadline = Instant::now() + Duration::from_millis(total_ms);
+    let replicas = &BACKEND_REPLICA_SERVICES[..state.backend_replicas.len().min(BACKEND_REPLICA_SERVICES.len())];
+    let launched = |service: &str| match state.launch_config.as_ref() {
+        Some(config) if service == "backend" => is_backend_required(config),
+        Some(config) if service == "mongo" => is_mongo_required(config),
+        Some(_) => true,
+        None => false,
+    };
+    let expected: Vec<&str> = ["web", "backend"]
+        .into_iter()
+        .chain(replicas.iter().copied())
+        .chain(["mongo"])
+        .filter(|service| launched(service))
+        .collect();
+    let mut report: Vec<DesktopRuntimeServiceStop> = Vec::new();
+    let mut forced: Vec<&str> = Vec::new();
+    for service in ["web", "backend"].into_iter().chain(replicas.iter().copied()).chain(["mongo"]) {
+        match stop_service_until(state, service, Some(deadline)) {
+            Some((stopped, cut_short)) => {
+                if cut_short {
+                    forced.push(service);
+                }
+                report.push(stopped);
+            }
+            None if expected.contains(&service) && !state.adopted_services.contains(service) => {
+                report.push(DesktopRuntimeServiceStop {
+                    service: service.to_string(),
+                    was_running: false,
+                    graceful: false,
+                    forced: false,
+                    exit_observed: false,
+                    stop_ms: 0,
+                });
+            }
+            None => {}
+        }
+    }
+    state.last_stop = report;
+    state.backend_replicas.clear();
+    if !forced.is_empty() {
+        push_runtime_event_with(
+            state,
+            "warn",
+            "runtime",
+            format!("Stop took longer than {total_ms}ms; force-killed {}", forced.join(", ")),
+            event_fields([
+                ("services", serde_json::Value::from(forced)),
+                ("stop_total_timeout_ms", total_ms.into()),
+            ]),
+        );
+    }
+    release_adopted_services(state);
+    state.running = false;
+}
+
+// An adopted mongod belongs to the user, so by default it is left running; `stop_adopted`
+// shuts it down through mongosh since there is no Child to kill.
+fn release_adopted_services(state: &mut RuntimeProcessState) {
+    let adopted: Vec<&'static str> = std::mem::take(&mut state.adopted_services).into_iter().collect();
+    let stop_adopted = state.launch_config.as_ref().map(|config| config.stop_adopted).unwrap_or(false);
+    for service in adopted {
+        if !stop_adopted {
+            push_runtime_event(state, "info", service, format!("Leaving adopted {service} running"));
+            continue;
+        }
+        let result = match state.launch_config.as_ref() {
+            Some(config) if service == "mongo" => shutdown_adopted_mongo(config),
+            _ => Err("no way to stop it".to_string()),
+        };
+        match result {
+            Ok(()) => push_runtime_event(state, "info", service, format!("Stopped adopted {service}")),
+            Err(err) => push_runtime_event(state, "warn", service, format!("Could not stop adopted {service}: {err}")),
+        }
+    }
+}
+
+// Leaves the session file alone: a failed start attempt stops through here too, and must not
+// wipe the crash-loop counters the next attempt restores. Real stops persist on their own.
+fn stop_all(state: &mut RuntimeProcessState) {
+    state.launch_generation = state.launch_generation.wrapping_add(1);
+    stop_processes(state);
+    clear_launch_state(state);
+}
+
+fn is_backend_required(config: &RuntimeLaunchConfig) -> bool {
+    config.mode == RuntimeMode::LocalFullstack
+}
+
+fn is_mongo_required(config: &RuntimeLaunchConfig) -> bool {
+    config.mode == RuntimeMode::LocalFullstack && config.mongo_bin.is_some()
+}
+
+fn recompute_running(state: &RuntimeProcessState) -> bool {
+    let Some(config) = state.launch_config.as_ref() else {
+        return false;
+    };
+    if state.observe_only {
+        return !state.observed_probes.is_empty() && state.observed_probes.iter().all(|probe| probe.ok);
+    }
+    if state.web.is_none() {
+        return false;
+    }
+    if is_backend_required(config) && state.backend.is_none() {
+        return false;
+    }
+    if backend_replica_services(config).iter().any(|service| !service_present(state, service)) {
+        return false;
+    }
+    if is_mongo_required(config) && !service_present(state, "mongo") {
+        return false;
+    }
+    true
+}
+
+fn service_present(state: &RuntimeProcessState, service: &str) -> bool {
+    let spawned = match service {
+        "web" => state.web.is_some(),
+        "backend" => state.backend.is_some(),
+        "mongo" => state.mongo.is_some(),
+        _ => backend_replica_index(service)
+            .and_then(|index| state.backend_replicas.get(index))
+            .is_some_and(Option::is_some),
+    };
+    spawned || state.adopted_services.contains(service)
+}
+
+fn service_owned(state: &RuntimeProcessState, service: &str) -> Option<bool> {
+    if state.adopted_services.contains(service) {
+        return Some(false);
+    }
+    service_present(state, service).then_some(true)
+}
+
+fn mongo_dbpath(config: &RuntimeLaunchConfig) -> Option<PathBuf> {
+    if let Some(db_dir) = config.mongo_dbpath.as_ref() {
+        return Some(db_dir.clone());
+    }
+    config.data_dir.as_ref().map(|dir| Path::new(dir).join("mongo"))
+}
+
+const MAX_LOG_BYTES: u64 = 5 * 1024 * 1024;
+const MAX_LOG_SEGMENTS: usize = 3;
+
+fn service_log_path(log_dir: &Path, service: &str) -> PathBuf {
+    log_dir.join(format!("{service}.log"))
+}
+
+fn rotated_log_path(path: &Path, index: usize) -> PathBuf {
+    let mut raw = path.as_os_str().to_os_string();
+    raw.push(format!(".{index}"));
+    PathBuf::from(raw)
+}
+
+struct ServiceLogWriter {
+    path: PathBuf,
+    file: fs::File,
+    written: u64,
+}
+
+impl ServiceLogWriter {
+    fn open(path: PathBuf) -> std::io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = fs::OpenOptions::new().create(true).append(true).open(&path)?;
+        let written = file.metadata().map(|meta| meta.len()).unwrap_or(0);
+        Ok(Self { path, file, written })
+    }
+
+    fn write_line(&mut self, line: &[u8]) {
+        if self.written >= MAX_LOG_BYTES {
+            self.rotate();
+        }
+        if self.file.write_all(line).is_ok() {
+            self.written = self.written.saturating_add(line.len() as u64);
+        }
+    }
+
+    // `<service>.log` -> `.log.1` -> ... -> `.log.N`; the oldest segment is overwritten.
+    fn rotate(&mut self) {
+        for index in (1..MAX_LOG_SEGMENTS).rev() {
+            let _ = fs::rename(rotated_log_path(&self.path, index), rotated_log_path(&self.path, index + 1));
+        }
+        let _ = fs::rename(&self.path, rotated_log_path(&self.path, 1));
+        if let Ok(file) = fs::OpenOptions::new().create(true).append(true).open(&self.path) {
+            self.file = file;
+            self.written = 0;
+        }
+    }
+}
+
+fn pump_output<R: Read + Send + 'static>(service: &str, reader: R, writer: Arc<Mutex<ServiceLogWriter>>) {
+    let _ = std::thread::Builder::new()
+        .name(format!("{service}-log"))
+        .spawn(move || {
+            let mut reader = BufReader::new(reader);
+            let mut line: Vec<u8> = Vec::new();
+            loop {
+                line.clear();
+                match reader.read_until(b'\n', &mut line) {
+                    Ok(0) | Err(_) => break,
+                    Ok(_) => {
+                        if let Ok(mut log) = writer.lock() {
+                            log.write_line(&line);
+                        }
+                    }
+                }
+            }
+        });
+}
+
+// Child stdout/stderr go to `<runtime>/logs/<service>.log`. If the log file can't be opened
+// the child inherits the shell's stdio instead, so a pipe is never left undrained.
+fn spawn_with_log_capture(command: &mut Command, service: &str, config: &RuntimeLaunchConfig) -> std::io::Result<Child> {
+    let writer = ServiceLogWriter::open(service_log_path(&config.log_dir, service)).ok();
+    if writer.is_some() {
+        command.stdout(Stdio::piped()).stderr(Stdio::piped());
+    }
+    let mut child = command.spawn()?;
+    if let Some(writer) = writer {
+        let writer = Arc::new(Mutex::new(writer));
+        if let Some(stdout) = child.stdout.take() {
+            pump_output(service, stdout, writer.clone());
+        }
+        if let Some(stderr) = child.stderr.take() {
+            pump_output(service, stderr, writer);
+        }
+    }
+    Ok(child)
+}
+
+// Precedence for child env vars: service-specific map > global `env` map > built-ins.
+fn apply_env_overrides(command: &mut Command, global: &BTreeMap<String, String>, service: &BTreeMap<String, String>) {
+    for (key, value) in global.iter().chain(service.iter()) {
+        command.env(key, value);
+    }
+}
+
+fn build_mongo_command(config: &RuntimeLaunchConfig) -> Option<Command> {
+    if config.mode != RuntimeMode::LocalFullstack {
+        return None;
+    }
+    let mongo_bin = config.mongo_bin.as_ref()?;
+    let mut mongo_cmd = Command::new(mongo_bin);
+    mongo_cmd.arg("--port").arg(config.mongo_port.to_string());
+    if let Some(db_dir) = mongo_dbpath(config) {
+        mongo_cmd.arg("--dbpath").arg(db_dir);
+    }
+    if config.mongo_auth.is_some() {
+        mongo_cmd.arg("--auth");
+    }
+    if let Some(correlation_id) = config.correlation_id.as_ref() {
+        mongo_cmd.env("PQA_CORRELATION_ID", correlation_id);
+    }
+    apply_env_overrides(&mut mongo_cmd, &config.env, &config.mongo_env);
+    Some(mongo_cmd)
+}
+
+fn is_transient_spawn_error(err: &std::io::Error) -> bool {
+    use std::io::ErrorKind;
+    match err.kind() {
+        ErrorKind::Interrupted | ErrorKind::WouldBlock | ErrorKind::TimedOut => true,
+        // On Windows "Access is denied" shows up briefly after a prior instance released the binary.
+        ErrorKind::PermissionDenied => cfg!(target_os = "windows"),
+        _ => {
+            // ETXTBSY ("text file busy") on Unix, ERROR_SHARING_VIOLATION on Windows.
+            let busy_code = if cfg!(target_os = "windows") { 32 } else { 26 };
+            err.raw_os_error() == Some(busy_code)
+        }
+    }
+}
+
+const SPAWN_ATTEMPTS: u32 = 3;
+
+fn spawn_with_retry(
+    command: &mut Command,
+    service: &str,
+    config: &RuntimeLaunchConfig,
+    on_retry: &mut dyn FnMut(String),
+) -> std::io::Result<Child> {
+    let mut attempt = 1;
+    loop {
+        match spawn_with_log_capture(command, service, config) {
+            Ok(child) => return Ok(child),
+            Err(err) if attempt < SPAWN_ATTEMPTS && is_transient_spawn_error(&err) => {
+                let delay = Duration::from_millis(250 * u64::from(attempt));
+                on_retry(format!(
+                    "{service} spawn failed ({err}); retrying in {}ms (attempt {}/{SPAWN_ATTEMPTS})",
+                    delay.as_millis(),
+                    attempt + 1
+                ));
+                std::thread::sleep(delay);
+                attempt += 1;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+fn retry_logger<'a>(state: &'a mut RuntimeProcessState, service: &'a str) -> impl FnMut(String) + 'a {
+    move |message| push_runtime_event(state, "warn", service, message)
+}
+
+fn spawn_mongo(config: &RuntimeLaunchConfig, on_retry: &mut dyn FnMut(String)) -> Result<Option<Child>, String> {
+    let Some(mut mongo_cmd) = build_mongo_command(config) else {
+        return Ok(None);
+    };
+    if let Some(db_dir) = mongo_dbpath(config) {
+        let _ = fs::create_dir_all(&db_dir);
+    }
+    let child = spawn_with_retry(&mut mongo_cmd, "mongo", config, on_retry)
+        .map_err(|err| format!("failed to start mongo sidecar: {err}"))?;
+    Ok(Some(child))
+}
+
+fn percent_encode_userinfo(raw: &str) -> String {
+    let mut out = String::with_capacity(raw.len());
+    for byte in raw.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            out.push(byte as char);
+        } else {
+            out.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    out
+}
+
+// mongod is spawned without --bind_ip, so it only listens on loopback: the URI always points
+// at LOCAL_BIND_HOST.
+fn mongo_uri(config: &RuntimeLaunchConfig) -> String {
+    let db = config.mongo_default_db.as_deref().unwrap_or("");
+    match config.mongo_auth.as_ref() {
+        Some(auth) => format!(
+            "mongodb://{}:{}@{LOCAL_BIND_HOST}:{}/{db}?authSource=admin",
+            percent_encode_userinfo(&auth.username),
+            percent_encode_userinfo(&auth.password),
+            config.mongo_port
+        ),
+        None if db.is_empty() => format!("mongodb://{LOCAL_BIND_HOST}:{}", config.mongo_port),
+        None => format!("mongodb://{LOCAL_BIND_HOST}:{}/{db}", config.mongo_port),
+    }
+}
+
+fn drain_pipe<R: Read + Send + 'static>(pipe: Option<R>) -> Option<std::thread::JoinHandle<Vec<u8>>> {
+    pipe.map(|mut pipe| {
+        std::thread::spawn(move || {
+            let mut buf = Vec::new();
+            let _ = pipe.read_to_end(&mut buf);
+            buf
+        })
+    })
+}
+
+// Runs a short-lived helper process, killing it if it outlives `timeout`. Output is drained
+// on threads so a chatty process can't stall on a full pipe.
+fn run_command_with_timeout(command: &mut Command, timeout: Duration) -> Result<(i32, String), String> {
+    let program = command.get_program().to_string_lossy().to_string();
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("failed to run {program}: {err}"))?;
+    let stdout = drain_pipe(child.stdout.take());
+    let stderr = drain_pipe(child.stderr.take());
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("{program} timed out after {}s", timeout.as_secs()));
+            }
+            Ok(None) => std::thread::sleep(Duration::from_millis(100)),
+            Err(err) => return Err(format!("failed to wait for {program}: {err}")),
+        }
+    };
+    let mut text = String::new();
+    for handle in [stdout, stderr].into_iter().flatten() {
+        text.push_str(&String::from_utf8_lossy(&handle.join().unwrap_or_default()));
+    }
+    Ok((status.code().unwrap_or(-1), text.trim().to_string()))
+}
+
+const MONGO_AUTH_TIMEOUT: Duration = Duration::from_secs(20);
+
+// Credentials reach mongosh through env vars so they never show up in the process list.
+fn mongosh_eval(config: &RuntimeLaunchConfig, auth: &MongoAuthConfig, script: &str) -> Result<(i32, String), String> {
+    let mut command = Command::new(&auth.mongosh_bin);
+    command
+        .arg("--quiet")
+        .arg("--port")
+        .arg(config.mongo_port.to_string())
+        .arg("--eval")
+        .arg(script)
+        .env("PQA_MONGO_USER", &auth.username)
+        .env("PQA_MONGO_PASSWORD", &auth.password);
+    run_command_with_timeout(&mut command, MONGO_AUTH_TIMEOUT)
+}
+
+fn shutdown_adopted_mongo(config: &RuntimeLaunchConfig) -> Result<(), String> {
+    let mut command = Command::new(
+        config
+            .mongo_auth
+            .as_ref()
+            .map(|auth| auth.mongosh_bin.as_str())
+            .unwrap_or("mongosh"),
+    );
+    command.arg("--quiet").arg("--port").arg(config.mongo_port.to_string());
+    let script = match config.mongo_auth.as_ref() {
+        Some(auth) => {
+            command
+                .env("PQA_MONGO_USER", &auth.username)
+                .env("PQA_MONGO_PASSWORD", &auth.password);
+            "const admin = db.getSiblingDB('admin'); admin.auth(process.env.PQA_MONGO_USER, process.env.PQA_MONGO_PASSWORD); admin.shutdownServer()"
+        }
+        None => "db.getSiblingDB('admin').shutdownServer()",
+    };
+    command.arg("--eval").arg(script);
+    // shutdownServer drops the connection, so mongosh's exit code says little; the port does.
+    let _ = run_command_with_timeout(&mut command, MONGO_AUTH_TIMEOUT)?;
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < deadline {
+        if !port_accepts(config.mongo_port) {
+            return Ok(());
+        }
+        std::thread::sleep(READY_POLL_INTERVAL);
+    }
+    Err(format!("mongo is still accepting connections on port {}", config.mongo_port))
+}
+
+// On a fresh dbpath the localhost exception allows creating the first user; on a reused
+// dbpath the user already exists and authenticating is enough.
+fn bootstrap_mongo_auth(config: &RuntimeLaunchConfig) -> Result<&'static str, String> {
+    let Some(auth) = config.mongo_auth.as_ref() else {
+        return Ok("disabled");
+    };
+    let check = "db.getSiblingDB('admin').auth(process.env.PQA_MONGO_USER, process.env.PQA_MONGO_PASSWORD)";
+    if let Ok((0, _)) = mongosh_eval(config, auth, check) {
+        return Ok("existing admin user authenticated");
+    }
+    let create = "db.getSiblingDB('admin').createUser({user: process.env.PQA_MONGO_USER, \
+                  pwd: process.env.PQA_MONGO_PASSWORD, roles: [{role: 'root', db: 'admin'}]})";
+    match mongosh_eval(config, auth, create) {
+        Ok((0, _)) => Ok("admin user created"),
+        Ok((code, output)) => Err(format!("mongo admin user creation failed (exit {code}): {output}")),
+        Err(err) => Err(format!("mongo admin user creation failed: {err}")),
+    }
+}
+
+// Shared by the backend sidecar and profile tasks so both see the same cwd and env.
+fn backend_env_command(config: &RuntimeLaunchConfig, program: &str) -> Command {
+    let mut backend_cmd = Command::new(program);
+    backend_cmd
+        .current_dir(&config.backend_dir)
+        .env("APP_RUNTIME_MODE", config.mode.as_backend_runtime_mode())
+        .env("APP_BACKEND_ORIGIN", "local")
+        .env("DESKTOP_SESSION_ID", config.desktop_session_id.clone())
+        .env(&config.mongo_uri_env_name, mongo_uri(config))
+        // Unbuffered so captured log lines show up as they are printed.
+        .env("PYTHONUNBUFFERED", "1");
+    if let Some(correlation_id) = config.correlation_id.as_ref() {
+        backend_cmd.env("PQA_CORRELATION_ID", correlation_id);
+    }
+    if let Some(profile_path) = config.runtime_profile_path.as_ref() {
+        backend_cmd.env("RUNTIME_PROFILE_PATH", profile_path);
+    }
+    if !config.backend_replica_ports.is_empty() {
+        backend_cmd.env("PQA_BACKEND_REPLICA", "0");
+    }
+    apply_env_overrides(&mut backend_cmd, &config.env, &config.backend_env);
+    backend_cmd
+}
+
+fn build_backend_command(config: &RuntimeLaunchConfig) -> Option<Command> {
+    if config.mode != RuntimeMode::LocalFullstack {
+        return None;
+    }
+    let mut backend_cmd = backend_env_command(config, &config.python_bin);
+    if let Some(debug) = config.backend_debug.as_ref() {
+        backend_cmd
+            .arg("-m")
+            .arg("debugpy")
+            .arg("--listen")
+            .arg(format!("{LOCAL_BIND_HOST}:{}", debug.port));
+        if debug.wait_for_client {
+            backend_cmd.arg("--wait-for-client");
+        }
+    }
+    backend_cmd
+        .arg("scripts/run_backend.py")
+        .arg("--host")
+        .arg(LOCAL_BIND_HOST)
+        .arg("--port")
+        .arg(config.backend_port.to_string())
+        .arg("--runtime-mode")
+        .arg(config.mode.as_backend_runtime_mode());
+    Some(backend_cmd)
+}
+
+fn spawn_backend(config: &RuntimeLaunchConfig, on_retry: &mut dyn FnMut(String)) -> Result<Option<Child>, String> {
+    let Some(mut backend_cmd) = build_backend_command(config) else {
+        return Ok(None);
+    };
+    let child = spawn_with_retry(&mut backend_cmd, "backend", config, on_retry)
+        .map_err(|err| format!("failed to start backend sidecar: {err}"))?;
+    Ok(Some(child))
+}
+
+// Same command as the first backend on the replica's own port, with its index in
+// PQA_BACKEND_REPLICA.
+fn build_backend_replica_command(config: &RuntimeLaunchConfig, index: usize) -> Option<Command> {
+    let mut replica = config.clone();
+    replica.backend_port = config.backend_replica_ports.get(index).copied()?;
+    let mut backend_cmd = build_backend_command(&replica)?;
+    backend_cmd.env("PQA_BACKEND_REPLICA", (index + 1).to_string());
+    Some(backend_cmd)
+}
+
+fn spawn_backend_replica(
+    config: &RuntimeLaunchConfig,
+    index: usize,
+    on_retry: &mut dyn FnMut(String),
+) -> Result<Option<Child>, String> {
+    let service = BACKEND_REPLICA_SERVICES[index];
+    if index >= config.backend_replica_ports.len() {
+        return Err(format!("{service} is not configured (backend_replicas)"));
+    }
+    let Some(mut backend_cmd) = build_backend_replica_command(config, index) else {
+        return Ok(None);
+    };
+    let child = spawn_with_retry(&mut backend_cmd, service, config, on_retry)
+        .map_err(|err| format!("failed to start {service} sidecar: {err}"))?;
+    Ok(Some(child))
+}
+
+fn build_web_command(config: &RuntimeLaunchConfig) -> Command {
+    let mut web_cmd = Command::new(npm_bin());
+    web_cmd
+        .current_dir(&config.web_dir)
+        .env("PORT", config.web_port.to_string())
+        .env("APP_RUNTIME_MODE", config.mode.as_backend_runtime_mode())
+        .env("DESKTOP_SESSION_ID", config.desktop_session_id.clone())
+        .env("NODE_ENV", if config.web_dev { "development" } else { "production" });
+    for name in &config.backend_url_env_names {
+        web_cmd.env(name, &config.backend_url);
+    }
+    if let Some(correlation_id) = config.correlation_id.as_ref() {
+        web_cmd.env("PQA_CORRELATION_ID", correlation_id);
+    }
+    if let Some(profile_path) = config.runtime_profile_path.as_ref() {
+        web_cmd.env("RUNTIME_PROFILE_PATH", profile_path);
+    }
+    if config.web_disable_tls_verification {
+        // Process-wide in Node: every outbound HTTPS call of the web server skips certificate
+        // checks, not only those to the local backend.
+        web_cmd.env("NODE_TLS_REJECT_UNAUTHORIZED", "0");
+    }
+    if config.web_dev {
+        web_cmd.arg("run").arg("dev");
+    } else {
+        web_cmd.arg("run").arg("start:standalone");
+        if let (Some(profile_path), Some(flag)) = (config.runtime_profile_path.as_ref(), config.web_profile_arg.as_ref()) {
+            web_cmd.arg("--").arg(flag).arg(profile_path);
+        }
+    }
+    apply_env_overrides(&mut web_cmd, &config.env, &config.web_env);
+    web_cmd
+}
+
+fn spawn_web(config: &RuntimeLaunchConfig, on_retry: &mut dyn FnMut(String)) -> Result<Child, String> {
+    spawn_with_retry(&mut build_web_command(config), "web", config, on_retry)
+        .map_err(|err| format!("failed to start web sidecar: {err}"))
+}
+
+fn is_secret_env_key(key: &str) -> bool {
+    let upper = key.to_ascii_uppercase();
+    ["SECRET", "TOKEN", "PASSWORD", "PASSWD", "API_KEY", "PRIVATE_KEY", "CREDENTIAL", "AUTH"]
+        .iter()
+        .any(|marker| upper.contains(marker))
+}
+
+fn redact_uri_credentials(value: &str) -> String {
+    let Some((scheme, rest)) = value.split_once("://") else {
+        return value.to_string();
+    };
+    let authority_end = rest.find('/').unwrap_or(rest.len());
+    match rest[..authority_end].rfind('@') {
+        Some(at) => format!("{scheme}://***@{}", &rest[at + 1..]),
+        None => value.to_string(),
+    }
+}
+
+fn redact_env_value(key: &str, value: &str) -> String {
+    if is_secret_env_key(key) {
+        return "***".to_string();
+    }
+    redact_uri_credentials(value)
+}
+
+// Env vars the shell hands the backend whose effective value it relies on afterwards, besides
+// the Mongo URI under `mongo_uri_env_name`.
+const VERIFIED_BACKEND_ENV: [&str; 4] = [
+    "APP_RUNTIME_MODE",
+    "APP_BACKEND_ORIGIN",
+    "DESKTOP_SESSION_ID",
+    "RUNTIME_PROFILE_PATH",
+];
+const BACKEND_CONFIG_TIMEOUT: Duration = Duration::from_secs(2);
+
+// After a start, asks the backend what config it actually runs with (`GET <backend_config_path>`,
+// a flat JSON object keyed by env var name) and warns about every value differing from the
+// one the shell set, e.g. a backend `.env` replacing MONGODB_URI. Keys it does not report are
+// not compared.
+fn verify_backend_config(state: &mut RuntimeProcessState, config: &RuntimeLaunchConfig) {
+    let Some(path) = config.backend_config_path.as_deref().filter(|_| is_backend_required(config)) else {
+        return;
+    };
+    let Some(command) = build_backend_command(config) else {
+        return;
+    };
+    let expected: BTreeMap<String, String> = command
+        .get_envs()
+        .filter_map(|(key, value)| Some((key.to_string_lossy().to_string(), value?.to_string_lossy().to_string())))
+        .filter(|(key, _)| VERIFIED_BACKEND_ENV.contains(&key.as_str()) || *key == config.mongo_uri_env_name)
+        .collect();
+    let url = join_url_path(&config.backend_url, path);
+    if let Some(reason) = https_unsupported(&url, "the backend config check") {
+        push_runtime_event(state, "debug", "backend", format!("Backend config not verified: {reason}"));
+        return;
+    }
+    let reported = match http_get(&url, BACKEND_CONFIG_TIMEOUT) {
+        Ok(response) if (200..300).contains(&response.status) => {
+            serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(&response.body)
+                .map_err(|err| format!("not a JSON object: {err}"))
+        }
+        Ok(response) => Err(format!("HTTP {}", response.status)),
+        Err(err) => Err(err),
+    };
+    let reported = match reported {
+        Ok(reported) => reported,
+        Err(err) => {
+            push_runtime_event(state, "debug", "backend", format!("Backend config not verified: GET {url}: {err}"));
+            return;
+        }
+    };
+    let mut mismatched: Vec<String> = Vec::new();
+    for (key, want) in &expected {
+        let Some(got) = reported.get(key) else {
+            continue;
+        };
+        let got = got.as_str().map(str::to_string).unwrap_or_else(|| got.to_string());
+        if &got == want {
+            continue;
+        }
+        mismatched.push(key.clone());
+        push_runtime_event_with(
+            state,
+            "warn",
+            "backend",
+            format!(
+                "Backend uses {key}={} but the shell set {}; the shell's view of it is wrong",
+                redact_env_value(key, &got),
+                redact_env_value(key, want)
+            ),
+            event_fields([
+                ("key", key.as_str().into()),
+                ("expected", redact_env_value(key, want).into()),
+                ("actual", redact_env_value(key, &got).into()),
+            ]),
+        );
+    }
+    if mismatched.is_empty() {
+        push_runtime_event(state, "debug", "backend", format!("Backend config matches the env the shell set ({url})"));
+    }
+}
+
+fn describe_env_overrides(config: &RuntimeLaunchConfig) -> String {
+    let groups = [
+        ("env", &config.env),
+        ("backend_env", &config.backend_env),
+        ("web_env", &config.web_env),
+        ("mongo_env", &config.mongo_env),
+    ];
+    let mut parts: Vec<String> = Vec::new();
+    for (name, vars) in groups {
+        if vars.is_empty() {
+            continue;
+        }
+        let entries = vars
+            .iter()
+            .map(|(key, value)| format!("{key}={}", redact_env_value(key, value)))
+            .collect::<Vec<_>>()
+            .join(",");
+        parts.push(format!("{name}[{entries}]"));
+    }
+    parts.join(" ")
+}
+
+fn describe_command(service: &str, command: &Command) -> DesktopRuntimeCommandPreview {
+    let env = command
+        .get_envs()
+        .map(|(key, value)| {
+            let key = key.to_string_lossy().to_string();
+            let value = value.map(|raw| redact_env_value(&key, &raw.to_string_lossy()));
+            (key, value)
+        })
+        .collect();
+    DesktopRuntimeCommandPreview {
+        service: service.to_string(),
+        program: command.get_program().to_string_lossy().to_string(),
+        args: command
+            .get_args()
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect(),
+        cwd: command
+            .get_current_dir()
+            .map(|dir| dir.to_string_lossy().to_string()),
+        env,
+    }
+}
+
+fn plan_launch_commands(config: &RuntimeLaunchConfig) -> Vec<DesktopRuntimeCommandPreview> {
+    let mut planned: Vec<DesktopRuntimeCommandPreview> = Vec::new();
+    if is_mongo_required(config) {
+        if let Some(command) = build_mongo_command(config) {
+            planned.push(describe_command("mongo", &command));
+        }
+    }
+    if is_backend_required(config) {
+        if let Some(command) = build_backend_command(config) {
+            planned.push(describe_command("backend", &command));
+        }
+        for (index, service) in backend_replica_services(config).iter().enumerate() {
+            if let Some(command) = build_backend_replica_command(config, index) {
+                planned.push(describe_command(service, &command));
+            }
+        }
+    }
+    planned.push(describe_command("web", &build_web_command(config)));
+    planned
+}
+
+// The vars `spawn_web` sets on top of the shell's own env, redacted.
+fn web_env_preview(config: &RuntimeLaunchConfig, source: &str) -> DesktopRuntimeWebEnv {
+    let env = build_web_command(config)
+        .get_envs()
+        .filter_map(|(key, value)| {
+            let key = key.to_string_lossy().to_string();
+            let value = redact_env_value(&key, &value?.to_string_lossy());
+            Some((key, value))
+        })
+        .collect();
+    DesktopRuntimeWebEnv {
+        mode: config.mode.as_str().to_string(),
+        source: source.to_string(),
+        env,
+    }
+}
+
+fn build_service_command(config: &RuntimeLaunchConfig, service: &str) -> Option<Command> {
+    match service {
+        "web" => Some(build_web_command(config)),
+        "backend" => build_backend_command(config),
+        "mongo" => build_mongo_command(config),
+        _ => backend_replica_index(service).and_then(|index| build_backend_replica_command(config, index)),
+    }
+}
+
+// The spawn functions build their commands from the config alone, so rebuilding one gives
+// exactly what was just executed.
+fn record_launched_command(state: &mut RuntimeProcessState, config: &RuntimeLaunchConfig, service: &str) {
+    if let Some(command) = build_service_command(config, service) {
+        state
+            .launched_commands
+            .insert(service.to_string(), describe_command(service, &command));
+    }
+}
+
+fn describe_exit(name: &str, status: std::process::ExitStatus) -> String {
+    if let Some(code) = status.code() {
+        return format!("{name} exited with code {code}");
+    }
+    format!("{name} exited")
+}
+
+// Each entry is (service, message, clean) where `clean` means a zero exit code.
+struct ProcessExit {
+    service: &'static str,
+    message: String,
+    clean: bool,
+    code: Option<i32>,
+}
+
+fn poll_process_exits(state: &mut RuntimeProcessState) -> Vec<ProcessExit> {
+    let mut exited: Vec<ProcessExit> = Vec::new();
+    for service in ["web", "backend", "mongo"].into_iter().chain(BACKEND_REPLICA_SERVICES) {
+        let Some(slot) = service_slot(state, service) else {
+            continue;
+        };
+        let Some(child) = slot.as_mut() else {
+            continue;
+        };
+        match child.try_wait() {
+            Ok(Some(status)) => {
+                exited.push(ProcessExit {
+                    service,
+                    message: describe_exit(service, status),
+                    clean: status.success(),
+                    code: status.code(),
+                });
+                *slot = None;
+            }
+            Ok(None) => {}
+            Err(_) => {
+                exited.push(ProcessExit {
+                    service,
+                    message: format!("{service} process status check failed"),
+                    clean: false,
+                    code: None,
+                });
+                *slot = None;
+            }
+        }
+    }
+    exited
+}
+
+// Restarts always use the mode captured in `launch_config`; a changed live env is only reported.
+fn warn_on_runtime_mode_drift(state: &mut RuntimeProcessState, config: &RuntimeLaunchConfig) {
+    let Ok(raw) = env::var("APP_RUNTIME_MODE") else {
+        return;
+    };
+    if raw.trim().is_empty() {
+        return;
+    }
+    let live_mode = RuntimeMode::from_raw(&raw);
+    if live_mode != config.mode {
+        push_runtime_event(
+            state,
+            "warn",
+            "watchdog",
+            format!(
+                "APP_RUNTIME_MODE={} differs from running mode {}; restarting with {}",
+                raw.trim(),
+                config.mode.as_str(),
+                config.mode.as_str()
+            ),
+        );
+    }
+}
+
+fn spawn_service(
+    config: &RuntimeLaunchConfig,
+    service: &str,
+    on_retry: &mut dyn FnMut(String),
+) -> Result<Option<Child>, String> {
+    match service {
+        "web" => spawn_web(config, on_retry).map(Some),
+        "backend" => spawn_backend(config, on_retry),
+        "mongo" => spawn_mongo(config, on_retry),
+        _ => match backend_replica_index(service) {
+            Some(index) => spawn_backend_replica(config, index, on_retry),
+            None => Err(format!("unknown service: {service}")),
+        },
+    }
+}
+
+// Spawns with the runtime lock released, so the backoff between spawn attempts does not stall
+// IPC; the retry messages are logged once the lock is held again, and the guard is returned.
+fn spawn_service_unlocked<'a>(
+    shared: &'a Mutex<RuntimeProcessState>,
+    config: &RuntimeLaunchConfig,
+    service: &'static str,
+) -> (Result<Option<Child>, String>, MutexGuard<'a, RuntimeProcessState>) {
+    let mut retries: Vec<String> = Vec::new();
+    let spawned = spawn_service(config, service, &mut |message| retries.push(message));
+    let mut state = lock_runtime(shared);
+    for message in retries {
+        push_runtime_event(&mut state, "warn", service, message);
+    }
+    (spawned, state)
+}
+
+// For a spawn that finished after the launch it belonged to was stopped or replaced.
+fn discard_spawned(spawned: Result<Option<Child>, String>) {
+    if let Ok(Some(mut child)) = spawned {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
+
+fn service_slot<'a>(state: &'a mut RuntimeProcessState, service: &str) -> Option<&'a mut Option<Child>> {
+    match service {
+        "web" => Some(&mut state.web),
+        "backend" => Some(&mut state.backend),
+        "mongo" => Some(&mut state.mongo),
+        _ => backend_replica_index(service).and_then(|index| state.backend_replicas.get_mut(index)),
+    }
+}
+
+const MAX_BACKEND_REPLICAS: u32 = 8;
+// Service names of the extra backend replicas; the first backend stays `backend`.
+const BACKEND_REPLICA_SERVICES: [&str; MAX_BACKEND_REPLICAS as usize - 1] =
+    ["backend-1", "backend-2", "backend-3", "backend-4", "backend-5", "backend-6", "backend-7"];
+
+fn backend_replica_index(service: &str) -> Option<usize> {
+    BACKEND_REPLICA_SERVICES.iter().position(|name| *name == service)
+}
+
+fn backend_replica_services(config: &RuntimeLaunchConfig) -> &'static [&'static str] {
+    &BACKEND_REPLICA_SERVICES[..config.backend_replica_ports.len()]
+}
+
+// `order` with the configured backend replicas right after `backend`.
+fn with_backend_replicas(config: &RuntimeLaunchConfig, order: &[&'static str]) -> Vec<&'static str> {
+    let mut services = Vec::with_capacity(order.len() + config.backend_replica_ports.len());
+    for service in order {
+        services.push(*service);
+        if *service == "backend" {
+            services.extend_from_slice(backend_replica_services(config));
+        }
+    }
+    services
+}
+
+fn is_service_missing(state: &RuntimeProcessState, config: &RuntimeLaunchConfig, service: &str) -> bool {
+    match service {
+        "web" => state.web.is_none(),
+        "backend" => is_backend_required(config) && state.backend.is_none(),
+        "mongo" => is_mongo_required(config) && !service_present(state, "mongo"),
+        _ => backend_replica_index(service).is_some_and(|index| {
+            index < config.backend_replica_ports.len()
+                && state.backend_replicas.get(index).is_none_or(Option::is_none)
+        }),
+    }
+}
+
+fn service_ready_port(config: &RuntimeLaunchConfig, service: &str) -> Option<u16> {
+    match service {
+        "web" => Some(config.web_port),
+        "backend" => Some(config.backend_port),
+        _ => backend_replica_index(service).and_then(|index| config.backend_replica_ports.get(index).copied()),
+    }
+}
+
+const MAX_RESTART_HISTORY: usize = 100;
+
+fn record_restart(state: &mut RuntimeProcessState, service: &str, reason: String, success: bool) {
+    state.restart_history.push(RestartRecord {
+        ts_ms: now_ms(),
+        service: service.to_string(),
+        reason,
+        success,
+    });
+    if state.restart_history.len() > MAX_RESTART_HISTORY {
+        let overflow = state.restart_history.len() - MAX_RESTART_HISTORY;
+        state.restart_history.drain(0..overflow);
+    }
+}
+
+const WATCHDOG_RESTART_ORDER: [&str; 3] = ["web", "backend", "mongo"];
+// Dependencies first, for the escalated full-stack restart.
+const FULL_RESTART_ORDER: [&str; 3] = ["mongo", "backend", "web"];
+
+// Runs on the watchdog thread. The lock is only held to inspect and install children;
+// readiness waits happen unlocked so IPC commands stay responsive during recovery.
+fn restart_missing_processes(
+    shared: &Mutex<RuntimeProcessState>,
+    order: [&'static str; 3],
+) -> Result<Vec<&'static str>, String> {
+    let (config, generation) = {
+        let mut state = lock_runtime(shared);
+        let Some(config) = state.launch_config.clone() else {
+            return Ok(Vec::new());
+        };
+        warn_on_runtime_mode_drift(&mut state, &config);
+        (config, state.launch_generation)
+    };
+    let mut restarted: Vec<&'static str> = Vec::new();
+
+    for service in with_backend_replicas(&config, &order) {
+        let (reason, log_offset) = {
+            let mut state = lock_runtime(shared);
+            if state.launch_generation != generation {
+                return Ok(restarted);
+            }
+            if !is_service_missing(&state, &config, service)
+                || state.parked_services.contains(service)
+                || state.restarting_services.contains(service)
+            {
+                continue;
+            }
+            state.restarting_services.insert(service);
+            let reason = match state.last_exit.remove(service) {
+                Some(exit) => format!("process exited: {exit}"),
+                None => "process missing".to_string(),
+            };
+            push_runtime_event(
+                &mut state,
+                "warn",
+                "watchdog",
+                format!("Restarting {service} sidecar ({reason})"),
+            );
+            (reason, log_len(&service_log_path(&config.log_dir, service)))
+        };
+        // restarting_services keeps other watchdog passes off the service meanwhile.
+        let (spawned, mut state) = spawn_service_unlocked(shared, &config, service);
+        if state.launch_generation != generation {
+            discard_spawned(spawned);
+            return Ok(restarted);
+        }
+        let child = match spawned {
+            Ok(Some(child)) => child,
+            Ok(None) => {
+                state.restarting_services.remove(service);
+                continue;
+            }
+            Err(err) => {
+                state.restarting_services.remove(service);
+                record_restart(&mut state, service, reason, false);
+                return Err(err);
+            }
+        };
+        if let Some(slot) = service_slot(&mut state, service) {
+            *slot = Some(child);
+        }
+        record_launched_command(&mut state, &config, service);
+        drop(state);
+        if let Err(err) = wait_for_restarted_service(shared, &config, service, log_offset) {
+            let mut state = lock_runtime(shared);
+            state.restarting_services.remove(service);
+            if state.launch_generation == generation {
+                if let Some(slot) = service_slot(&mut state, service) {
+                    stop_child(slot);
+                }
+                record_restart(&mut state, service, reason, false);
+            }
+            return Err(err);
+        }
+        {
+            let mut state = lock_runtime(shared);
+            state.restarting_services.remove(service);
+            record_restart(&mut state, service, reason, true);
+        }
+        restarted.push(service);
+    }
+
+    if !restarted.is_empty() {
+        let mut state = lock_runtime(shared);
+        if state.launch_generation == generation {
+            state.restart_count = state.restart_count.saturating_add(1);
+            state.last_restart_ms = Some(now_ms());
+            persist_runtime_session(&state);
+            let services = restarted.iter().map(|service| serde_json::Value::from(*service)).collect();
+            push_runtime_event_with(
+                &mut state,
+                "info",
+                "watchdog",
+                format!("Recovered sidecars: {}", restarted.join(", ")),
+                event_fields([("services", serde_json::Value::Array(services))]),
+            );
+        }
+    }
+    Ok(restarted)
+}
+
+fn pick_free_port() -> Result<u16, String> {
+    let listener = TcpListener::bind(("127.0.0.1", 0)).map_err(|err| format!("no spare port available: {err}"))?;
+    listener
+        .local_addr()
+        .map(|addr| addr.port())
+        .map_err(|err| format!("no spare port available: {err}"))
+}
+
+// There is no bind-host setting: the backend and mongod always listen on loopback, and the
+// standalone Next.js server binds every interface unless HOSTNAME is set. These are the
+// addresses a port scan tests, so a port only counts as free when both can be bound.
+const LOCAL_BIND_HOST: &str = "127.0.0.1";
+const WILDCARD_BIND_HOST: &str = "0.0.0.0";
+const SCANNED_BIND_HOSTS: [&str; 2] = [LOCAL_BIND_HOST, WILDCARD_BIND_HOST];
+const MAX_PORT_SCAN: u32 = 1024;
+
+fn port_bindable(port: u16) -> bool {
+    SCANNED_BIND_HOSTS
+        .into_iter()
+        .all(|host| TcpListener::bind((host, port)).is_ok())
+}
+
+fn scan_free_ports(start: u16, end: u16) -> Result<DesktopRuntimePortScan, String> {
+    if start == 0 || start > end {
+        return Err(format!("invalid port range {start}-{end}"));
+    }
+    let requested = u32::from(end) - u32::from(start) + 1;
+    let truncated = requested > MAX_PORT_SCAN;
+    let last = if truncated {
+        (u32::from(start) + MAX_PORT_SCAN - 1) as u16
+    } else {
+        end
+    };
+    let free: Vec<u16> = (start..=last).filter(|port| port_bindable(*port)).collect();
+    Ok(DesktopRuntimePortScan {
+        start,
+        end: last,
+        free,
+        hosts: SCANNED_BIND_HOSTS.iter().map(|host| host.to_string()).collect(),
+        truncated,
+        note: truncated.then(|| {
+            format!("range of {requested} ports capped at {MAX_PORT_SCAN}; scanned {start}-{last} only")
+        }),
+    })
+}
+
+fn local_backend_url(scheme: &str, port: u16) -> String {
+    format!("{scheme}://127.0.0.1:{port}")
+}
+
+fn set_service_port(config: &mut RuntimeLaunchConfig, service: &str, port: u16) {
+    match service {
+        "web" => config.web_port = port,
+        "backend" => {
+            config.backend_port = port;
+            if config.mode == RuntimeMode::LocalFullstack {
+                config.backend_url = local_backend_url(config.local_backend_scheme, port);
+            }
+        }
+        _ => {}
+    }
+}
+
+// Replaces a running service in place: stop, respawn, wait for readiness.
+fn restart_service_in_place(
+    shared: &Mutex<RuntimeProcessState>,
+    config: &RuntimeLaunchConfig,
+    generation: u64,
+    service: &'static str,
+) -> Result<(), String> {
+    let log_offset = {
+        let mut state = lock_runtime(shared);
+        stop_service(&mut state, service);
+        log_len(&service_log_path(&config.log_dir, service))
+    };
+    let (spawned, mut state) = spawn_service_unlocked(shared, config, service);
+    if state.launch_generation != generation {
+        discard_spawned(spawned);
+        return Err("runtime was restarted or stopped during the restart".to_string());
+    }
+    let child = spawned?;
+    if let Some(slot) = service_slot(&mut state, service) {
+        *slot = child;
+    }
+    record_launched_command(&mut state, config, service);
+    drop(state);
+    wait_for_restarted_service(shared, config, service, log_offset).inspect_err(|_| {
+        let mut state = lock_runtime(shared);
+        if state.launch_generation == generation {
+            if let Some(slot) = service_slot(&mut state, service) {
+                stop_child(slot);
+            }
+        }
+    })
+}
+
+// Brings `service` up on a spare port from `next`, which is updated to that port, and waits
+// for it; a replacement that does not become ready is killed again.
+fn spawn_replacement(
+    shared: &Mutex<RuntimeProcessState>,
+    next: &mut RuntimeLaunchConfig,
+    service: &'static str,
+) -> Result<Child, String> {
+    let port = pick_free_port()?;
+    set_service_port(next, service, port);
+    let log_offset = log_len(&service_log_path(&next.log_dir, service));
+    let (spawned, state) = spawn_service_unlocked(shared, next, service);
+    drop(state);
+    let mut replacement = spawned?.ok_or_else(|| format!("{service} is not launched in the current mode"))?;
+    let timeout = service_ready_timeout(next, service, Duration::from_secs(30));
+    let needed = next.readiness_consecutive_ok;
+    let mut exited = || exit_status(Some(&mut replacement)).is_some();
+    if wait_for_port(port, timeout, needed, &mut exited).is_none() {
+        let reason = match exit_status(Some(&mut replacement)) {
+            Some(status) => startup_exit_failure(next, service, status, log_offset),
+            None => format!("replacement {service} did not become ready on port {port}"),
+        };
+        let _ = replacement.kill();
+        let _ = replacement.wait();
+        return Err(reason);
+    }
+    Ok(replacement)
+}
+
+// Zero-downtime variant: the replacement comes up on a spare port and the old process is only
+// stopped once the new one is ready. Web reads BACKEND_BASE_URL only when it is spawned, so a
+// backend swap also brings up a new web against the new backend, then stops the old web before
+// the old backend, which finishes its in-flight requests within `backend_stop_grace_ms`.
+fn restart_service_graceful(
+    shared: &Mutex<RuntimeProcessState>,
+    config: &RuntimeLaunchConfig,
+    generation: u64,
+    service: &'static str,
+) -> Result<(), String> {
+    let swapped: &[&'static str] = if service == "backend" { &["backend", "web"] } else { &["web"] };
+    let mut next = config.clone();
+    let mut replacements: Vec<(&'static str, Child)> = Vec::new();
+    let kill_all = |replacements: Vec<(&'static str, Child)>| {
+        for (_, mut child) in replacements {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    };
+    for name in swapped {
+        match spawn_replacement(shared, &mut next, name) {
+            Ok(child) => replacements.push((name, child)),
+            Err(err) => {
+                kill_all(replacements);
+                return Err(err);
+            }
+        }
+    }
+    let mut state = lock_runtime(shared);
+    if state.launch_generation != generation {
+        kill_all(replacements);
+        return Err("runtime was restarted or stopped during graceful restart".to_string());
+    }
+    for (name, replacement) in replacements.into_iter().rev() {
+        stop_service(&mut state, name);
+        if let Some(slot) = service_slot(&mut state, name) {
+            *slot = Some(replacement);
+        }
+        record_launched_command(&mut state, &next, name);
+    }
+    state.web_port = next.web_port;
+    state.backend_port = next.backend_port;
+    state.backend_url = next.backend_url.clone();
+    let message = match service {
+        "backend" => format!(
+            "backend moved to port {} (web to port {}) after graceful restart",
+            next.backend_port, next.web_port
+        ),
+        _ => format!("{service} moved to port {} after graceful restart", next.web_port),
+    };
+    let fields = event_fields([
+        ("service", service.into()),
+        ("web_port", next.web_port.into()),
+        ("backend_port", next.backend_port.into()),
+    ]);
+    state.launch_config = Some(next);
+    push_runtime_event_with(&mut state, "info", "runtime", message, fields);
+    Ok(())
+}
+
+fn restart_service(shared: &Mutex<RuntimeProcessState>, service: &str, reason: &str) -> Result<(), String> {
+    let service: &'static str = match service.trim().to_lowercase().as_str() {
+        "web" => "web",
+        "backend" => "backend",
+        "mongo" => "mongo",
+        other => match backend_replica_index(other) {
+            Some(index) => BACKEND_REPLICA_SERVICES[index],
+            None => return Err(format!("unknown service: {other}")),
+        },
+    };
+    let (config, generation) = {
+        let mut state = lock_runtime(shared);
+        let Some(config) = state.launch_config.clone() else {
+            return Err("runtime is not started".to_string());
+        };
+        let launched = match service {
+            "backend" => is_backend_required(&config),
+            "mongo" => is_mongo_required(&config),
+            "web" => true,
+            _ => is_backend_required(&config) && backend_replica_services(&config).contains(&service),
+        };
+        if !launched {
+            return Err(format!("{service} is not launched in mode {}", config.mode.as_str()));
+        }
+        if state.adopted_services.contains(service) {
+            return Err(format!("{service} was adopted, not started by this shell; restart it where it was started"));
+        }
+        if state.observe_only {
+            return Err("observe-only: the shell does not restart services it did not start".to_string());
+        }
+        if state.restarting_services.contains(service) {
+            return Err(format!("{service} is already restarting"));
+        }
+        state.parked_services.remove(service);
+        state.restarting_services.insert(service);
+        push_runtime_event(&mut state, "info", "runtime", format!("Restart requested for {service}"));
+        (config, state.launch_generation)
+    };
+    // Mongo stays on its port: the backend's MONGODB_URI is fixed when it is spawned.
+    let graceful = config.graceful_restart
+        && matches!(service, "web" | "backend")
+        && service_ready_port(&config, service).is_some();
+    if config.graceful_restart && !graceful {
+        let mut state = lock_runtime(shared);
+        push_runtime_event(
+            &mut state,
+            "info",
+            "runtime",
+            format!("Graceful restart not supported for {service}; restarting in place"),
+        );
+    }
+    let result = if graceful {
+        restart_service_graceful(shared, &config, generation, service)
+    } else {
+        restart_service_in_place(shared, &config, generation, service)
+    };
+    let mut state = lock_runtime(shared);
+    state.restarting_services.remove(service);
+    state.last_exit.remove(service);
+    record_restart(&mut state, service, reason.to_string(), result.is_ok());
+    let fields = event_fields([
+        ("service", service.into()),
+        ("reason", reason.into()),
+        ("success", result.is_ok().into()),
+    ]);
+    match &result {
+        Ok(()) => push_runtime_event_with(&mut state, "info", "runtime", format!("{service} restarted"), fields),
+        Err(err) => {
+            let message = format!("Restart of {service} failed: {err}");
+            push_runtime_event_with(&mut state, "error", "runtime", message.clone(), fields);
+            state.last_error = Some(message);
+        }
+    }
+    state.running = recompute_running(&state);
+    result
+}
+
+fn reconcile_runtime_state(state: &mut RuntimeProcessState) {
+    // An adopted service has no Child to poll; it counts as gone once its port stops answering.
+    if state.adopted_services.contains("mongo") {
+        let port = state.launch_config.as_ref().map(|config| config.mongo_port);
+        if let Some(port) = port.filter(|port| !port_accepts(*port)) {
+            state.adopted_services.remove("mongo");
+            let message = format!("adopted mongo stopped accepting connections on port {port}");
+            push_runtime_event_with(state, "warn", "mongo", message.clone(), event_fields([("service", "mongo".into())]));
+            state.last_exit.insert("mongo", message.clone());
+            state.last_error = Some(message);
+        }
+    }
+    let exited = poll_process_exits(state);
+    if !exited.is_empty() {
+        let mut parts: Vec<String> = Vec::new();
+        for exit in &exited {
+            let source = exit.service;
+            let mut fields = event_fields([
+                ("service", source.into()),
+                ("clean", exit.clean.into()),
+            ]);
+            if let Some(code) = exit.code {
+                fields.insert("code".to_string(), serde_json::Value::from(code));
+            }
+            push_runtime_event_with(state, "warn", source, exit.message.clone(), fields);
+            state.last_exit.insert(source, exit.message.clone());
+            parts.push(exit.message.clone());
+            let policy = state
+                .launch_config
+                .as_ref()
+                .map(|config| config.restart_policies.for_service(source))
+                .unwrap_or(RestartPolicy::Always);
+            let park_reason = match policy {
+                RestartPolicy::Never => Some("restart_policy=never, not restarting"),
+                RestartPolicy::OnFailure if exit.clean => Some("clean exit, not restarting"),
+                _ => None,
+            };
+            if let Some(reason) = park_reason {
+                state.parked_services.insert(source);
+                push_runtime_event(state, "info", "watchdog", format!("{source}: {reason}"));
+            }
+        }
+        state.last_error = Some(parts.join(" | "));
+        state.last_crash_ms = Some(now_ms());
+    }
+
+    state.running = recompute_running(state);
+    state.last_reconcile_ms = Some(now_ms());
+}
+
+// When auto-restart was disabled and should come back on its own: `autorestart_cooldown_ms`
+// after the later of the disable and the last sidecar exit.
+fn autorestart_cooldown_until(state: &RuntimeProcessState) -> Option<u64> {
+    let cooldown = state.launch_config.as_ref()?.autorestart_cooldown_ms?;
+    let disabled_ms = state.auto_restart_disabled_ms?;
+    if state.auto_restart || state.observe_only {
+        return None;
+    }
+    Some(disabled_ms.max(state.last_crash_ms.unwrap_or(0)).saturating_add(cooldown))
+}
+
+// Re-arms a watchdog that gave up after a crash loop once things have been quiet for the
+// cooldown. Services a restart_policy parked stay down.
+fn reenable_auto_restart_after_cooldown(state: &mut RuntimeProcessState) {
+    let Some(until) = autorestart_cooldown_until(state) else {
+        return;
+    };
+    if now_ms() < until {
+        return;
+    }
+    let quiet_ms = state.launch_config.as_ref().and_then(|config| config.autorestart_cooldown_ms).unwrap_or(0);
+    state.restart_count = 0;
+    state.last_restart_ms = None;
+    state.failed_restart_ms.clear();
+    state.escalated_restart_ms = None;
+    state.auto_restart = true;
+    state.auto_restart_disabled_ms = None;
+    persist_runtime_session(state);
+    push_runtime_event_with(
+        state,
+        "info",
+        "watchdog",
+        format!("Auto-restart re-enabled after {}s without sidecar crashes", quiet_ms / 1000),
+        event_fields([("quiet_ms", quiet_ms.into())]),
+    );
+}
+
+const RESTART_WINDOW_MS: u64 = 90_000;
+const RESTART_BUDGET: u32 = 6;
+
+fn restart_due(state: &mut RuntimeProcessState) -> bool {
+    let Some(config) = state.launch_config.as_ref() else {
+        return false;
+    };
+    let restartable = with_backend_replicas(config, &["web", "backend", "mongo"])
+        .into_iter()
+        .any(|service| is_service_missing(state, config, service) && !state.parked_services.contains(service));
+    if !state.auto_restart || !restartable {
+        return false;
+    }
+    !restart_budget_exhausted(state)
+}
+
+// Disables auto-restart once RESTART_BUDGET restarts happened within RESTART_WINDOW_MS.
+fn restart_budget_exhausted(state: &mut RuntimeProcessState) -> bool {
+    let now = now_ms();
+    let recently_restarted = state
+        .last_restart_ms
+        .map(|last| now.saturating_sub(last) < RESTART_WINDOW_MS)
+        .unwrap_or(false);
+    if recently_restarted && state.restart_count >= RESTART_BUDGET {
+        disable_auto_restart(state, "Auto-restart disabled after repeated sidecar failures");
+        return true;
+    }
+    false
+}
+
+const DEFAULT_OBSERVE_INTERVAL_MS: u64 = 5_000;
+
+// Observe-only replacement for the watchdog: probes the configured endpoints (unlocked) and
+// derives `running` from them, logging when an observed service goes up or down.
+fn run_observe_pass(shared: &Mutex<RuntimeProcessState>, force: bool) {
+    let plan = {
+        let mut state = lock_runtime(shared);
+        let Some(config) = state.launch_config.as_ref() else {
+            return;
+        };
+        let interval = config.health_check_interval_ms.unwrap_or(DEFAULT_OBSERVE_INTERVAL_MS);
+        let now = now_ms();
+        let due = state
+            .last_observe_ms
+            .map(|last| now.saturating_sub(last) >= interval)
+            .unwrap_or(true);
+        if !state.observe_only || !(due || force) {
+            return;
+        }
+        state.last_observe_ms = Some(now);
+        health_probe_plan(&state)
+    };
+    let probes = run_health_probes(&plan);
+    let mut state = lock_runtime(shared);
+    if !state.observe_only {
+        return;
+    }
+    for probe in &probes {
+        let previous = state
+            .observed_probes
+            .iter()
+            .find(|old| old.target == probe.target)
+            .map(|old| old.ok);
+        if previous == Some(probe.ok) {
+            continue;
+        }
+        let (level, message) = if probe.ok {
+            ("info", format!("Observed {} is up", probe.target))
+        } else {
+            ("warn", format!("Observed {} is down: {}", probe.target, probe.message))
+        };
+        push_runtime_event_with(
+            &mut state,
+            level,
+            "watchdog",
+            message,
+            event_fields([("service", probe.target.as_str().into()), ("ok", probe.ok.into())]),
+        );
+    }
+    remember_backend_health(&mut state, &probes);
+    state.observed_probes = probes;
+    state.running = recompute_running(&state);
+    state.last_reconcile_ms = Some(now_ms());
+}
+
+const ESCALATE_AFTER_FAILED_RESTARTS: usize = 3;
+
+// Counts failed watchdog restarts within RESTART_WINDOW_MS. Returns true when they call for
+// one full-stack restart; repeated failures after an escalation disable auto-restart instead.
+fn record_failed_restart(state: &mut RuntimeProcessState) -> bool {
+    let now = now_ms();
+    state.failed_restart_ms.retain(|ts| now.saturating_sub(*ts) < RESTART_WINDOW_MS);
+    state.failed_restart_ms.push(now);
+    if state.failed_restart_ms.len() < ESCALATE_AFTER_FAILED_RESTARTS {
+        return false;
+    }
+    let escalated_recently = state
+        .escalated_restart_ms
+        .map(|ts| now.saturating_sub(ts) < RESTART_WINDOW_MS)
+        .unwrap_or(false);
+    if escalated_recently {
+        disable_auto_restart(state, "Auto-restart disabled: sidecars keep failing after a full-stack restart");
+        return false;
+    }
+    state.failed_restart_ms.clear();
+    state.escalated_restart_ms = Some(now);
+    true
+}
+
+// Without `autorestart_cooldown_ms`, auto-restart stays off once disabled; this is when the
+// restart budget window would have let the watchdog try again, reported so the UI can offer
+// a retry instead. With a cooldown it is when the watchdog re-enables itself.
+fn restart_backoff_until(state: &RuntimeProcessState) -> Option<u64> {
+    let disabled_ms = state.auto_restart_disabled_ms?;
+    let config = state.launch_config.as_ref()?;
+    let down = with_backend_replicas(config, &["web", "backend", "mongo"])
+        .into_iter()
+        .any(|service| is_service_missing(state, config, service));
+    if state.auto_restart || state.observe_only || !down {
+        return None;
+    }
+    autorestart_cooldown_until(state)
+        .or_else(|| Some(state.last_restart_ms.unwrap_or(disabled_ms).saturating_add(RESTART_WINDOW_MS)))
+}
+
+// Clears the restart budget and failure bookkeeping, then restarts every down sidecar now.
+fn retry_down_services(shared: &Mutex<RuntimeProcessState>) -> Result<Vec<&'static str>, String> {
+    {
+        let mut state = lock_runtime(shared);
+        let Some(config) = state.launch_config.clone() else {
+            return Err("runtime is not started".to_string());
+        };
+        if state.observe_only {
+            return Err("observe-only: the shell does not restart services it did not start".to_string());
+        }
+        reconcile_runtime_state(&mut state);
+        let down: Vec<&'static str> = with_backend_replicas(&config, &WATCHDOG_RESTART_ORDER)
+            .into_iter()
+            .filter(|service| is_service_missing(&state, &config, service))
+            .collect();
+        if down.is_empty() {
+            push_runtime_event(&mut state, "info", "watchdog", "Retry requested but no sidecar is down");
+            return Ok(Vec::new());
+        }
+        for service in &down {
+            state.parked_services.remove(service);
+        }
+        state.restart_count = 0;
+        state.last_restart_ms = None;
+        state.failed_restart_ms.clear();
+        state.escalated_restart_ms = None;
+        state.auto_restart = true;
+        state.auto_restart_disabled_ms = None;
+        persist_runtime_session(&state);
+        push_runtime_event_with(
+            &mut state,
+            "info",
+            "watchdog",
+            format!("Forced retry of {}; restart backoff cleared", down.join(", ")),
+            event_fields([(
+                "services",
+                serde_json::Value::Array(down.iter().map(|service| serde_json::Value::from(*service)).collect()),
+            )]),
+        );
+    }
+    let result = restart_missing_processes(shared, WATCHDOG_RESTART_ORDER);
+    let mut state = lock_runtime(shared);
+    state.running = recompute_running(&state);
+    result.map_err(|err| {
+        let message = format!("Forced retry failed: {err}");
+        push_runtime_event(&mut state, "error", "watchdog", message.clone());
+        state.last_error = Some(message.clone());
+        message
+    })
+}
+
+fn disable_auto_restart(state: &mut RuntimeProcessState, message: &str) {
+    state.auto_restart = false;
+    state.auto_restart_disabled_ms = Some(now_ms());
+    persist_runtime_session(state);
+    push_runtime_event(state, "error", "watchdog", message.to_string());
+    state.last_error = Some(message.to_string());
+}
+
+// A lone service restart can keep failing because a dependency died unnoticed; stopping
+// everything and respawning in dependency order recovers from that.
+fn restart_full_stack(shared: &Mutex<RuntimeProcessState>) -> bool {
+    {
+        let mut state = lock_runtime(shared);
+        push_runtime_event(
+            &mut state,
+            "warn",
+            "watchdog",
+            format!(
+                "Escalating: {ESCALATE_AFTER_FAILED_RESTARTS} sidecar restarts failed within {}s; restarting the whole stack",
+                RESTART_WINDOW_MS / 1000
+            ),
+        );
+        let services = match state.launch_config.as_ref() {
+            Some(config) => with_backend_replicas(config, &FULL_RESTART_ORDER),
+            None => FULL_RESTART_ORDER.to_vec(),
+        };
+        for service in services {
+            if service_slot(&mut state, service).is_some_and(|slot| slot.is_some()) {
+                stop_service(&mut state, service);
+                state.last_exit.insert(service, "stopped for full-stack restart".to_string());
+            }
+        }
+        state.running = false;
+    }
+    let result = restart_missing_processes(shared, FULL_RESTART_ORDER);
+    let mut state = lock_runtime(shared);
+    match result {
+        Ok(_) => {
+            push_runtime_event(&mut state, "info", "watchdog", "Full-stack restart succeeded".to_string());
+            true
+        }
+        Err(err) => {
+            disable_auto_restart(
+                &mut state,
+                &format!("Auto-restart disabled: full-stack restart failed: {err}"),
+            );
+            false
+        }
+    }
+}
+
+fn run_watchdog_pass(shared: &Arc<Mutex<RuntimeProcessState>>) {
+    let due = {
+        let mut state = lock_runtime(shared);
+        reconcile_runtime_state(&mut state);
+        if state.watchdog_suspended {
+            return;
+        }
+        reenable_auto_restart_after_cooldown(&mut state);
+        restart_due(&mut state)
+    };
+    if !due {
+        return;
+    }
+    let result = restart_missing_processes(shared, WATCHDOG_RESTART_ORDER);
+    let mut state = lock_runtime(shared);
+    let mut recovered = matches!(&result, Ok(restarted) if !restarted.is_empty());
+    if let Err(err) = result {
+        let message = format!("Auto-restart failed: {err}");
+        push_runtime_event(&mut state, "error", "watchdog", message.clone());
+        state.last_error = Some(message);
+        if record_failed_restart(&mut state) {
+            drop(state);
+            recovered = restart_full_stack(shared);
+            state = lock_runtime(shared);
+        }
+    }
+    state.running = recompute_running(&state);
+    let rerun_hook = state
+        .launch_config
+        .as_ref()
+        .and_then(|config| config.on_ready.as_ref())
+        .is_some_and(|hook| hook.after_recovery);
+    if recovered && state.running && rerun_hook {
+        spawn_on_ready_hook(shared, &mut state, "recovery");
+    }
+    if state.running && state.start_phase.as_deref() == Some("degraded") {
+        state.start_phase = Some("recovered".to_string());
+        state.last_error = None;
+        push_runtime_event(&mut state, "info", "watchdog", "Degraded start recovered: all sidecars running".to_string());
+    }
+}
+
+// Active probing on top of the liveness check: a service whose process is alive but fails
+// `max_unhealthy_before_restart` consecutive probes is restarted like a crashed one.
+fn run_health_pass(shared: &Mutex<RuntimeProcessState>) {
+    let (plan, max_unhealthy, generation) = {
+        let mut state = lock_runtime(shared);
+        let Some(config) = state.launch_config.clone() else {
+            return;
+        };
+        let Some(interval) = config.health_check_interval_ms else {
+            return;
+        };
+        // A process paused in a debugger fails every probe; don't count those against it.
+        if state.watchdog_suspended {
+            return;
+        }
+        let now = now_ms();
+        let due = state
+            .last_health_check_ms
+            .map(|last| now.saturating_sub(last) >= interval)
+            .unwrap_or(true);
+        if !state.running || !due {
+            return;
+        }
+        state.last_health_check_ms = Some(now);
+        let plan: Vec<(&'static str, ProbeKind)> = health_probe_plan(&state)
+            .into_iter()
+            .filter(|(service, _)| service_present(&state, service) && !state.parked_services.contains(service))
+            .collect();
+        (plan, config.max_unhealthy_before_restart, state.launch_generation)
+    };
+    if plan.is_empty() {
+        return;
+    }
+    let probes = run_health_probes(&plan);
+    let unhealthy: Vec<(&'static str, String)> = {
+        let mut state = lock_runtime(shared);
+        if state.launch_generation != generation {
+            return;
+        }
+        remember_backend_health(&mut state, &probes);
+        let mut unhealthy = Vec::new();
+        for ((service, _), probe) in plan.iter().zip(probes.iter()) {
+            let previous = state.unhealthy_streaks.get(service).copied().unwrap_or(0);
+            if probe.ok {
+                if previous > 0 {
+                    state.unhealthy_streaks.remove(service);
+                    push_runtime_event_with(
+                        &mut state,
+                        "info",
+                        "watchdog",
+                        format!("{service} is healthy again"),
+                        event_fields([("service", (*service).into()), ("healthy", true.into())]),
+                    );
+                }
+                continue;
+            }
+            let streak = previous.saturating_add(1);
+            state.unhealthy_streaks.insert(service, streak);
+            if previous == 0 {
+                push_runtime_event_with(
+                    &mut state,
+                    "warn",
+                    "watchdog",
+                    format!("{service} health check failing: {}", probe.message),
+                    event_fields([
+                        ("service", (*service).into()),
+                        ("latency_ms", probe.latency_ms.into()),
+                    ]),
+                );
+            }
+            if streak >= max_unhealthy && state.auto_restart {
+                unhealthy.push((*service, probe.message.clone()));
+            }
+        }
+        unhealthy
+    };
+    for (service, probe_message) in unhealthy {
+        {
+            let mut state = lock_runtime(shared);
+            if state.launch_generation != generation || restart_budget_exhausted(&mut state) {
+                return;
+            }
+            push_runtime_event(
+                &mut state,
+                "warn",
+                "watchdog",
+                format!(
+                    "{service} process unhealthy (still running, {max_unhealthy} failed checks); restarting"
+                ),
+            );
+        }
+        let reason = format!("process unhealthy: {max_unhealthy} consecutive failed checks, last: {probe_message}");
+        let restarted = restart_service(shared, service, &reason).is_ok();
+        let mut state = lock_runtime(shared);
+        if state.launch_generation == generation {
+            if restarted {
+                state.unhealthy_streaks.remove(service);
+            }
+            state.unhealthy_restart_count = state.unhealthy_restart_count.saturating_add(1);
+            state.restart_count = state.restart_count.saturating_add(1);
+            state.last_restart_ms = Some(now_ms());
+            persist_runtime_session(&state);
+        }
+    }
+}
+
+const WATCH_DEBOUNCE_MS: u64 = 1_000;
+const WATCH_MAX_FILES: usize = 5_000;
+const WATCH_SKIP_DIRS: [&str; 6] = [".git", "__pycache__", "node_modules", ".venv", ".mypy_cache", ".pytest_cache"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct WatchFingerprint {
+    files: usize,
+    mtime_sum: u64,
+    newest_ms: u64,
+}
+
+fn scan_watch_paths(paths: &[PathBuf]) -> WatchFingerprint {
+    let mut fingerprint = WatchFingerprint {
+        files: 0,
+        mtime_sum: 0,
+        newest_ms: 0,
+    };
+    let mut pending: Vec<PathBuf> = paths.to_vec();
+    while let Some(path) = pending.pop() {
+        if fingerprint.files >= WATCH_MAX_FILES {
+            break;
+        }
+        let Ok(meta) = fs::metadata(&path) else {
+            continue;
+        };
+        if meta.is_dir() {
+            let skip = path
+                .file_name()
+                .map(|name| WATCH_SKIP_DIRS.iter().any(|dir| name == *dir))
+                .unwrap_or(false);
+            if skip {
+                continue;
+            }
+            if let Ok(entries) = fs::read_dir(&path) {
+                pending.extend(entries.flatten().map(|entry| entry.path()));
+            }
+            continue;
+        }
+        let modified = meta
+            .modified()
+            .ok()
+            .and_then(|ts| ts.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        fingerprint.files += 1;
+        fingerprint.mtime_sum = fingerprint.mtime_sum.wrapping_add(modified);
+        fingerprint.newest_ms = fingerprint.newest_ms.max(modified);
+    }
+    fingerprint
+}
+
+// Polling watcher driven by the watchdog tick; a change restarts only the backend, once the
+// tree has been quiet for WATCH_DEBOUNCE_MS. While the watchdog is suspended a settled change
+// stays pending and restarts the backend after the resume.
+fn run_watch_pass(shared: &Mutex<RuntimeProcessState>) {
+    let (paths, replicas) = {
+        let state = lock_runtime(shared);
+        match state.launch_config.as_ref() {
+            Some(config) if state.running && !config.watch_paths.is_empty() => {
+                (config.watch_paths.clone(), backend_replica_services(config))
+            }
+            _ => return,
+        }
+    };
+    let fingerprint = scan_watch_paths(&paths);
+    let trigger = {
+        let mut state = lock_runtime(shared);
+        let now = now_ms();
+        match state.watch_fingerprint {
+            None => state.watch_fingerprint = Some(fingerprint),
+            Some(previous) if previous != fingerprint => {
+                state.watch_fingerprint = Some(fingerprint);
+                state.watch_pending_since_ms = Some(now);
+            }
+            Some(_) => {}
+        }
+        let quiet = !state.watchdog_suspended
+            && state
+                .watch_pending_since_ms
+                .map(|since| now.saturating_sub(since) >= WATCH_DEBOUNCE_MS)
+                .unwrap_or(false);
+        if quiet {
+            state.watch_pending_since_ms = None;
+            push_runtime_event(&mut state, "info", "watch", "Source change detected; restarting backend");
+        }
+        quiet
+    };
+    if trigger {
+        for service in ["backend"].into_iter().chain(replicas.iter().copied()) {
+            let _ = restart_service(shared, service, "watched source changed");
+        }
+    }
+}
+
+const LOG_BUDGET_CHECK_INTERVAL_MS: u64 = 10_000;
+
+// Rotated segments (`<service>.log.N`) in the log dir, oldest first, plus the size of the
+// whole dir. Active `.log` files are counted but never pruned.
+fn scan_log_dir(log_dir: &Path) -> (u64, Vec<(PathBuf, u64)>) {
+    let mut total = 0;
+    let mut rotated: Vec<(SystemTime, PathBuf, u64)> = Vec::new();
+    for entry in fs::read_dir(log_dir).into_iter().flatten().flatten() {
+        let Ok(meta) = entry.metadata() else {
+            continue;
+        };
+        if !meta.is_file() {
+            continue;
+        }
+        total += meta.len();
+        let name = entry.file_name().to_string_lossy().to_string();
+        let is_rotated = name
+            .rsplit_once(".log.")
+            .is_some_and(|(_, index)| !index.is_empty() && index.bytes().all(|byte| byte.is_ascii_digit()));
+        if is_rotated {
+            let modified = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            rotated.push((modified, entry.path(), meta.len()));
+        }
+    }
+    rotated.sort_by_key(|(modified, _, _)| *modified);
+    (total, rotated.into_iter().map(|(_, path, len)| (path, len)).collect())
+}
+
+// Keeps the log dir under `max_total_log_bytes` by deleting the oldest rotated segments across
+// all services; each service rotates on its own, so together they could still fill the disk.
+fn run_log_budget_pass(shared: &Mutex<RuntimeProcessState>) {
+    let (log_dir, budget) = {
+        let mut state = lock_runtime(shared);
+        let Some((log_dir, budget)) = state
+            .launch_config
+            .as_ref()
+            .and_then(|config| config.max_total_log_bytes.map(|budget| (config.log_dir.clone(), budget)))
+        else {
+            return;
+        };
+        let now = now_ms();
+        let due = state
+            .last_log_budget_check_ms
+            .is_none_or(|last| now.saturating_sub(last) >= LOG_BUDGET_CHECK_INTERVAL_MS);
+        if !due {
+            return;
+        }
+        state.last_log_budget_check_ms = Some(now);
+        (log_dir, budget)
+    };
+    let (mut total, rotated) = scan_log_dir(&log_dir);
+    let mut pruned: Vec<String> = Vec::new();
+    let mut freed: u64 = 0;
+    for (path, len) in rotated {
+        if total <= budget {
+            break;
+        }
+        if fs::remove_file(&path).is_ok() {
+            total = total.saturating_sub(len);
+            freed += len;
+            pruned.push(path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default());
+        }
+    }
+    let mut state = lock_runtime(shared);
+    let over = total > budget;
+    if !pruned.is_empty() {
+        let mut message = format!("Log budget: pruned {} ({freed} bytes) to stay under {budget} bytes", pruned.join(", "));
+        if over {
+            message.push_str(&format!("; still {total} bytes in active logs"));
+        }
+        push_runtime_event_with(
+            &mut state,
+            if over { "warn" } else { "info" },
+            "watchdog",
+            message,
+            event_fields([
+                ("pruned", serde_json::Value::from(pruned)),
+                ("freed_bytes", freed.into()),
+                ("total_bytes", total.into()),
+            ]),
+        );
+    } else if over && !state.log_budget_exceeded {
+        push_runtime_event_with(
+            &mut state,
+            "warn",
+            "watchdog",
+            format!("Log budget: {total} bytes in active logs exceed max_total_log_bytes ({budget}); nothing left to prune"),
+            event_fields([("total_bytes", total.into())]),
+        );
+    }
+    state.log_budget_exceeded = over;
+}
+
+const DRIFT_CHECK_INTERVAL_MS: u64 = 5_000;
+
+// Dotted paths (`local_ports.backend`, `env.FOO`) where two profile documents differ; nested
+// objects are compared key by key, anything else as a whole.
+fn profile_drift(running: &serde_json::Value, current: &serde_json::Value, prefix: &str, out: &mut Vec<String>) {
+    let (Some(running), Some(current)) = (running.as_object(), current.as_object()) else {
+        if running != current {
+            out.push(prefix.to_string());
+        }
+        return;
+    };
+    let keys: BTreeSet<&String> = running.keys().chain(current.keys()).collect();
+    for key in keys {
+        let path = if prefix.is_empty() { key.clone() } else { format!("{prefix}.{key}") };
+        match (running.get(key), current.get(key)) {
+            (Some(before), Some(after)) => profile_drift(before, after, &path, out),
+            _ => out.push(path),
+        }
+    }
+}
+
+// Re-reads the profile the running stack was started from whenever its mtime changes and
+// records which fields now differ. An unreadable or invalid file shows up as `profile_path`.
+fn run_config_drift_pass(shared: &Mutex<RuntimeProcessState>) {
+    let (path, running) = {
+        let mut state = lock_runtime(shared);
+        let Some((path, running)) = state.launch_profile.clone().filter(|_| state.launch_config.is_some()) else {
+            return;
+        };
+        let now = now_ms();
+        if state.last_drift_check_ms.is_some_and(|last| now.saturating_sub(last) < DRIFT_CHECK_INTERVAL_MS) {
+            return;
+        }
+        state.last_drift_check_ms = Some(now);
+        (path, running)
+    };
+    let mtime = fs::metadata(&path).and_then(|meta| meta.modified()).ok();
+    if mtime.is_some() && lock_runtime(shared).profile_checked_mtime == mtime {
+        return;
+    }
+    let current = fs::read_to_string(&path)
+        .ok()
+        .and_then(|raw| serde_json::from_str::<serde_json::Value>(&raw).ok());
+    let mut fields: Vec<String> = Vec::new();
+    match current {
+        Some(current) => profile_drift(&running, &current, "", &mut fields),
+        None => fields.push("profile_path".to_string()),
+    }
+    let mut state = lock_runtime(shared);
+    if state.launch_config.is_none() {
+        return;
+    }
+    state.profile_checked_mtime = mtime;
+    if fields == state.config_drift_fields {
+        return;
+    }
+    let message = if fields.is_empty() {
+        "Profile matches the running config again".to_string()
+    } else {
+        format!("Profile changed since start ({}); restart to apply", fields.join(", "))
+    };
+    push_runtime_event_with(
+        &mut state,
+        "info",
+        "runtime",
+        message,
+        event_fields([("fields", serde_json::Value::from(fields.clone()))]),
+    );
+    state.config_drift_fields = fields;
+}
+
+const WATCHDOG_INTERVAL_MS: u64 = 1_000;
+const WATCHDOG_JITTER_MS: u64 = 100;
+
+fn spawn_runtime_watchdog(shared: Arc<Mutex<RuntimeProcessState>>) {
+    let worker = shared.clone();
+    let spawned = std::thread::Builder::new()
+        .name("runtime-watchdog".to_string())
+        .spawn(move || loop {
+            std::thread::sleep(jittered(
+                Duration::from_millis(WATCHDOG_INTERVAL_MS),
+                Duration::from_millis(WATCHDOG_JITTER_MS),
+            ));
+            // Reconciles first thing, with auto-restart off or the watchdog suspended too, so an
+            // exited sidecar is reaped within a tick even while the UI sends no commands.
+            run_watchdog_pass(&worker);
+            run_health_pass(&worker);
+            run_watch_pass(&worker);
+            run_log_budget_pass(&worker);
+            run_config_drift_pass(&worker);
+        });
+    if let Err(err) = spawned {
+        let mut state = lock_runtime(&shared);
+        push_runtime_event(&mut state, "error", "watchdog", format!("failed to start watchdog thread: {err}"));
+    }
+}
+
+// Set from the signal handler and picked up by the signal thread; the handler itself only
+// stores the number, which is all that is async-signal-safe.
+#[cfg(unix)]
+static SHUTDOWN_SIGNAL: std::sync::atomic::AtomicI32 = std::sync::atomic::AtomicI32::new(0);
+
+// Set by the signal thread before it takes the runtime lock. A start in progress holds that
+// lock, so its readiness waits and retries check this and give up instead of finishing first.
+static SHUTDOWN_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+fn shutdown_requested() -> bool {
+    SHUTDOWN_REQUESTED.load(Ordering::SeqCst)
+}
+
+#[cfg(unix)]
+const SHUTDOWN_SIGNALS: [(libc::c_int, &str); 3] =
+    [(libc::SIGHUP, "HUP"), (libc::SIGINT, "INT"), (libc::SIGTERM, "TERM")];
+
+// kill(2) on one of the shell's own children.
+#[cfg(unix)]
+fn send_signal(pid: u32, signum: libc::c_int) -> std::io::Result<()> {
+    // SAFETY: kill(2) on a pid of our own child; no memory is shared.
+    if unsafe { libc::kill(pid as libc::pid_t, signum) } == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+#[cfg(unix)]
+extern "C" fn record_shutdown_signal(signum: libc::c_int) {
+    SHUTDOWN_SIGNAL.store(signum, Ordering::SeqCst);
+}
+
+const SIGNAL_STOP_GRACE: Duration = Duration::from_secs(5);
+
+// A supervisor's SIGTERM (or Ctrl-C, or a hangup) would otherwise end the shell and orphan the
+// sidecars. They get SIGINT for SIGINT and SIGTERM otherwise (a forwarded HUP would only make
+// the backend reload), a grace period to exit, then the normal stop; the shell then exits
+// with 128 + signal.
+#[cfg(unix)]
+fn shutdown_on_signal(shared: &Mutex<RuntimeProcessState>, signum: i32) -> ! {
+    let name = SHUTDOWN_SIGNALS
+        .iter()
+        .find(|(number, _)| *number == signum)
+        .map(|(_, name)| *name)
+        .unwrap_or("TERM");
+    let (forward, forward_signum) = if name == "INT" { ("INT", libc::SIGINT) } else { ("TERM", libc::SIGTERM) };
+    SHUTDOWN_REQUESTED.store(true, Ordering::SeqCst);
+    {
+        let mut state = lock_runtime(shared);
+        state.auto_restart = false;
+        let pids: Vec<(&str, u32)> = ["web", "backend", "mongo"]
+            .into_iter()
+            .chain(BACKEND_REPLICA_SERVICES)
+            .filter_map(|service| {
+                service_slot(&mut state, service)
+                    .and_then(|slot| slot.as_ref().map(|child| (service, child.id())))
+            })
+            .collect();
+        push_runtime_event(
+            &mut state,
+            "warn",
+            "runtime",
+            format!("Shell received SIG{name}; sending SIG{forward} to sidecars and stopping"),
+        );
+        for (service, pid) in pids {
+            if let Err(err) = send_signal(pid, forward_signum) {
+                push_runtime_event(&mut state, "warn", service, format!("SIG{forward} not sent: {err}"));
+            }
+        }
+    }
+    let deadline = Instant::now() + SIGNAL_STOP_GRACE;
+    while Instant::now() < deadline {
+        let mut guard = lock_runtime(shared);
+        let state = &mut *guard;
+        let alive = [&mut state.web, &mut state.backend, &mut state.mongo]
+            .into_iter()
+            .chain(state.backend_replicas.iter_mut())
+            .any(|slot| slot.as_mut().is_some_and(|child| matches!(child.try_wait(), Ok(None))));
+        drop(guard);
+        if !alive {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    let mut state = lock_runtime(shared);
+    stop_all(&mut state);
+    persist_runtime_session(&state);
+    push_runtime_event(&mut state, "info", "runtime", "Runtime stopped");
+    std::process::exit(128 + signum);
+}
+
+#[cfg(unix)]
+fn install_shutdown_signal_handlers(shared: Arc<Mutex<RuntimeProcessState>>) {
+    let worker = shared.clone();
+    let spawned = std::thread::Builder::new()
+        .name("runtime-signals".to_string())
+        .spawn(move || loop {
+            std::thread::sleep(Duration::from_millis(100));
+            let signum = SHUTDOWN_SIGNAL.swap(0, Ordering::SeqCst);
+            if signum != 0 {
+                shutdown_on_signal(&worker, signum);
+            }
+        });
+    match spawned {
+        Ok(_) => {
+            let handler = record_shutdown_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+            for (signum, name) in SHUTDOWN_SIGNALS {
+                // SAFETY: the handler only stores into an atomic.
+                if unsafe { libc::signal(signum, handler) } == libc::SIG_ERR {
+                    let mut state = lock_runtime(&shared);
+                    push_runtime_event(&mut state, "warn", "runtime", format!("SIG{name} handler not installed"));
+                }
+            }
+        }
+        Err(err) => {
+            let mut state = lock_runtime(&shared);
+            push_runtime_event(&mut state, "error", "runtime", format!("failed to start signal thread: {err}"));
+        }
+    }
+}
+
+const RECONCILE_DEBOUNCE_MS: u64 = 250;
+
+// Status reads poll frequently; skip the try_wait pass if one ran just now.
+fn reconcile_if_stale(state: &mut RuntimeProcessState) {
+    let fresh = state
+        .last_reconcile_ms
+        .map(|last| now_ms().saturating_sub(last) < RECONCILE_DEBOUNCE_MS)
+        .unwrap_or(false);
+    if !fresh {
+        reconcile_runtime_state(state);
+    }
+}
+
+fn backend_replica_status(state: &RuntimeProcessState) -> Vec<DesktopRuntimeBackendReplica> {
+    let Some(config) = state.launch_config.as_ref().filter(|config| !config.backend_replica_ports.is_empty()) else {
+        return Vec::new();
+    };
+    let checked = state.last_health_check_ms.is_some();
+    let replica = |service: &str, port: u16, pid: Option<u32>| DesktopRuntimeBackendReplica {
+        service: service.to_string(),
+        port,
+        pid,
+        healthy: checked.then(|| !state.unhealthy_streaks.contains_key(service)),
+    };
+    let mut replicas = vec![replica("backend", state.backend_port, state.backend.as_ref().map(Child::id))];
+    for (index, (service, port)) in backend_replica_services(config).iter().zip(&config.backend_replica_ports).enumerate() {
+        let pid = state.backend_replicas.get(index).and_then(|slot| slot.as_ref().map(Child::id));
+        replicas.push(replica(service, *port, pid));
+    }
+    replicas
+}
+
+fn snapshot_status(state: &RuntimeProcessState) -> DesktopRuntimeStatus {
+    DesktopRuntimeStatus {
+        running: state.running,
+        mode: state.mode.as_str().to_string(),
+        web_pid: state.web.as_ref().map(|c| c.id()),
+        backend_pid: state.backend.as_ref().map(|c| c.id()),
+        mongo_pid: state.mongo.as_ref().map(|c| c.id()),
+        web_owned: service_owned(state, "web"),
+        backend_owned: service_owned(state, "backend"),
+        mongo_owned: service_owned(state, "mongo"),
+        backend_debug_port: state
+            .launch_config
+            .as_ref()
+            .and_then(|config| config.backend_debug.map(|debug| debug.port)),
+        backend_replicas: backend_replica_status(state),
+        started_at_ms: state.started_at_ms,
+        last_error: state.last_error.clone(),
+        web_port: state.web_port,
+        backend_port: state.backend_port,
+        mongo_port: state.mongo_port,
+        backend_url: state.backend_url.clone(),
+        auto_restart: state.auto_restart,
+        restart_count: state.restart_count,
+        unhealthy_restart_count: state.unhealthy_restart_count,
+        last_restart_ms: state.last_restart_ms,
+        restart_backoff_until_ms: restart_backoff_until(state),
+        diagnostics_path: state
+            .diagnostics_path
+            .as_ref()
+            .filter(|_| state.persist_diagnostics)
+            .map(|path| path.to_string_lossy().to_string()),
+        planned_commands: None,
+        port_adjusted: state.port_adjusted,
+        workspace_root: state
+            .workspace_root
+            .as_ref()
+            .map(|path| path.to_string_lossy().to_string()),
+        web_dir: state.web_dir.as_ref().map(|path| path.to_string_lossy().to_string()),
+        backend_dir: state
+            .backend_dir
+            .as_ref()
+            .map(|path| path.to_string_lossy().to_string()),
+        desktop_session_id: state.desktop_session_id.clone(),
+        correlation_id: state.correlation_id.clone(),
+        instance_label: state.instance_label.clone(),
+        backend_scheme: parse_http_url(&state.backend_url)
+            .map(|target| target.scheme)
+            .unwrap_or_else(|_| "http".to_string()),
+        mongo_dbpath: state
+            .mongo_dbpath
+            .as_ref()
+            .map(|path| path.to_string_lossy().to_string()),
+        mongo_fresh: state.mongo_fresh,
+        launch_warnings: state.launch_warnings.clone(),
+        start_phase: state.start_phase.clone(),
+        start_progress: state.start_progress,
+        observe_only: state.observe_only,
+        watchdog_suspended: state.watchdog_suspended,
+        restart_in_progress: !state.restarting_services.is_empty(),
+        restarting_services: state.restarting_services.iter().map(|service| service.to_string()).collect(),
+        config_drift: !state.config_drift_fields.is_empty(),
+        config_drift_fields: state.config_drift_fields.clone(),
+        last_stop: state.last_stop.clone(),
+        observed: state.observed_probes.clone(),
+        backend_health: state.backend_health.clone(),
+    }
+}
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone)]
+enum ProbeKind {
+    Mongo(u16),
+    Http(String),
+}
+
+const OP_MSG: i32 = 2013;
+const MAX_MONGO_REPLY_BYTES: usize = 16 * 1024 * 1024;
+
+// `{isMaster: 1, $db: "admin"}` as an OP_MSG. isMaster (rather than hello) is answered by every
+// mongod since 3.6, and neither needs authentication.
+fn mongo_is_master_message() -> Vec<u8> {
+    let mut doc: Vec<u8> = vec![0; 4];
+    doc.push(0x10);
+    doc.extend_from_slice(b"isMaster\0");
+    doc.extend_from_slice(&1i32.to_le_bytes());
+    doc.push(0x02);
+    doc.extend_from_slice(b"$db\0");
+    doc.extend_from_slice(&6i32.to_le_bytes());
+    doc.extend_from_slice(b"admin\0");
+    doc.push(0);
+    let doc_len = doc.len() as i32;
+    doc[..4].copy_from_slice(&doc_len.to_le_bytes());
+    let mut message: Vec<u8> = Vec::with_capacity(21 + doc.len());
+    message.extend_from_slice(&((21 + doc.len()) as i32).to_le_bytes());
+    message.extend_from_slice(&1i32.to_le_bytes());
+    message.extend_from_slice(&0i32.to_le_bytes());
+    message.extend_from_slice(&OP_MSG.to_le_bytes());
+    message.extend_from_slice(&0u32.to_le_bytes());
+    message.push(0);
+    message.extend_from_slice(&doc);
+    message
+}
+
+// The reply's top-level `ok`, a double (an int32 from some servers). isMaster replies have no
+// nested `ok`, so the first match is the top-level one.
+fn mongo_reply_ok(doc: &[u8]) -> Option<bool> {
+    let at = doc.windows(4).position(|window| window[1..] == *b"ok\0" && matches!(window[0], 0x01 | 0x10))?;
+    let value = &doc[at + 4..];
+    match doc[at] {
+        0x01 => Some(f64::from_le_bytes(value.get(..8)?.try_into().ok()?) == 1.0),
+        _ => Some(i32::from_le_bytes(value.get(..4)?.try_into().ok()?) == 1),
+    }
+}
+
+// A TCP connect only shows the port is open; this checks mongod answers commands.
+fn mongo_ping(port: u16, timeout: Duration) -> Result<(), String> {
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    let mut stream = TcpStream::connect_timeout(&addr, timeout).map_err(|err| err.to_string())?;
+    let _ = stream.set_read_timeout(Some(timeout));
+    let _ = stream.set_write_timeout(Some(timeout));
+    stream
+        .write_all(&mongo_is_master_message())
+        .map_err(|err| format!("isMaster not sent: {err}"))?;
+    let mut header = [0u8; 16];
+    stream
+        .read_exact(&mut header)
+        .map_err(|err| format!("no isMaster reply: {err}"))?;
+    let length = i32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
+    let op_code = i32::from_le_bytes([header[12], header[13], header[14], header[15]]);
+    if op_code != OP_MSG || !(21..=MAX_MONGO_REPLY_BYTES).contains(&length) {
+        return Err(format!("unexpected reply to isMaster (opcode {op_code}, {length} bytes)"));
+    }
+    let mut body = vec![0u8; length - 16];
+    stream
+        .read_exact(&mut body)
+        .map_err(|err| format!("truncated isMaster reply: {err}"))?;
+    match mongo_reply_ok(&body[5..]) {
+        Some(true) => Ok(()),
+        Some(false) => Err("isMaster returned ok: 0".to_string()),
+        None => Err("isMaster reply has no ok field".to_string()),
+    }
+}
+
+fn tcp_reachable(host: &str, port: u16, timeout: Duration) -> Result<SocketAddr, String> {
+    let addrs = (host, port)
+        .to_socket_addrs()
+        .map_err(|err| format!("cannot resolve {host}: {err}"))?;
+    let mut last_error = format!("{host} has no addresses");
+    for addr in addrs {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(_) => return Ok(addr),
+            Err(err) => last_error = format!("tcp {addr}: {err}"),
+        }
+    }
+    Err(last_error)
+}
+
+fn probe_address(url: &str) -> Option<String> {
+    parse_http_url(url).ok().map(|target| format!("{}:{}", target.host, target.port))
+}
+
+fn run_probe(target: &str, kind: &ProbeKind) -> DesktopRuntimeProbe {
+    let started = Instant::now();
+    let mut probe = DesktopRuntimeProbe {
+        target: target.to_string(),
+        ok: false,
+        latency_ms: 0,
+        message: String::new(),
+        kind: "http".to_string(),
+        address: None,
+        http_status: None,
+        error: None,
+        body: None,
+    };
+    match kind {
+        ProbeKind::Mongo(port) => {
+            let addr = SocketAddr::from(([127, 0, 0, 1], *port));
+            probe.kind = "mongo".to_string();
+            probe.address = Some(addr.to_string());
+            match mongo_ping(*port, PROBE_TIMEOUT) {
+                Ok(()) => {
+                    probe.ok = true;
+                    probe.message = format!("mongo {addr} answered isMaster");
+                }
+                Err(err) => {
+                    probe.message = format!("mongo {addr}: {err}");
+                    probe.error = Some(err);
+                }
+            }
+        }
+        // No TLS stack in the shell: an https target only gets a TCP reachability check.
+        ProbeKind::Http(url) if url.trim_start().to_ascii_lowercase().starts_with("https://") => {
+            probe.kind = "tcp_tls".to_string();
+            probe.address = probe_address(url);
+            match parse_http_url(url).and_then(|target| tcp_reachable(&target.host, target.port, PROBE_TIMEOUT)) {
+                Ok(addr) => {
+                    probe.ok = true;
+                    probe.address = Some(addr.to_string());
+                    probe.message = format!("tcp {addr} accepted (tls not verified)");
+                }
+                Err(err) => {
+                    probe.message = format!("{url}: {err}");
+                    probe.error = Some(err);
+                }
+            }
+        }
+        ProbeKind::Http(url) => {
+            probe.address = probe_address(url);
+            match http_get(url, PROBE_TIMEOUT) {
+                Ok(response) => {
+                    probe.ok = (200..400).contains(&response.status);
+                    probe.http_status = Some(response.status);
+                    probe.message = format!("GET {url} -> {}", response.status);
+                    if probe.ok {
+                        probe.body = Some(response.body);
+                    } else {
+                        probe.error = Some(format!("HTTP {}", response.status));
+                    }
+                }
+                Err(err) => {
+                    probe.message = format!("GET {url}: {err}");
+                    probe.error = Some(err);
+                }
+            }
+        }
+    }
+    probe.latency_ms = started.elapsed().as_millis() as u64;
+    probe
+}
+
+const MAX_BACKEND_HEALTH_CHARS: usize = 4096;
+
+// Keeps the backend's self-reported health (version, db state, ...) from the latest passing probe.
+fn remember_backend_health(state: &mut RuntimeProcessState, probes: &[DesktopRuntimeProbe]) {
+    let body = probes
+        .iter()
+        .filter(|probe| probe.ok && matches!(probe.target.as_str(), "backend" | "remote_backend"))
+        .find_map(|probe| probe.body.as_deref());
+    let Some(body) = body else {
+        return;
+    };
+    state.backend_health = Some(serde_json::from_str::<serde_json::Value>(body).unwrap_or_else(|_| {
+        serde_json::Value::String(body.trim().chars().take(MAX_BACKEND_HEALTH_CHARS).collect())
+    }));
+}
+
+fn health_probe_plan(state: &RuntimeProcessState) -> Vec<(&'static str, ProbeKind)> {
+    let mode = state
+        .launch_config
+        .as_ref()
+        .map(|config| config.mode)
+        .unwrap_or(state.mode);
+    let mut plan: Vec<(&'static str, ProbeKind)> = vec![(
+        "web",
+        ProbeKind::Http(format!("http://127.0.0.1:{}/", state.web_port)),
+    )];
+    match mode {
+        RuntimeMode::LocalFullstack => {
+            plan.push((
+                "backend",
+                ProbeKind::Http(join_url_path(&state.backend_url, "/health/ready")),
+            ));
+            if let Some(config) = state.launch_config.as_ref() {
+                for (service, port) in backend_replica_services(config).iter().zip(&config.backend_replica_ports) {
+                    let url = local_backend_url(config.local_backend_scheme, *port);
+                    plan.push((service, ProbeKind::Http(join_url_path(&url, "/health/ready"))));
+                }
+            }
+            plan.push(("mongo", ProbeKind::Mongo(state.mongo_port)));
+        }
+        RuntimeMode::RemoteSlim => {
+            plan.push((
+                "remote_backend",
+                ProbeKind::Http(join_url_path(&state.backend_url, "/health/live")),
+            ));
+        }
+    }
+    plan
+}
+
+// Probes run in parallel so the command returns within roughly one PROBE_TIMEOUT.
+fn run_health_probes(plan: &[(&'static str, ProbeKind)]) -> Vec<DesktopRuntimeProbe> {
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = plan
+            .iter()
+            .map(|(target, kind)| scope.spawn(move || run_probe(target, kind)))
+            .collect();
+        handles
+            .into_iter()
+            .zip(plan.iter())
+            .map(|(handle, (target, _))| {
+                handle.join().unwrap_or_else(|_| DesktopRuntimeProbe {
+                    target: target.to_string(),
+                    ok: false,
+                    latency_ms: 0,
+                    message: "probe panicked".to_string(),
+                    kind: String::new(),
+                    address: None,
+                    http_status: None,
+                    error: Some("probe panicked".to_string()),
+                    body: None,
+                })
+            })
+            .collect()
+    })
+}
+
+// Ordered by how bad it is, so the worst state within a bucket wins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum ServiceHealth {
+    Unknown,
+    Stopped,
+    Up,
+    Unhealthy,
+    Down,
+}
+
+impl ServiceHealth {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Unknown => "unknown",
+            Self::Stopped => "stopped",
+            Self::Up => "up",
+            Self::Unhealthy => "unhealthy",
+            Self::Down => "down",
+        }
+    }
+}
+
+struct HealthChange {
+    ts_ms: u64,
+    service: String,
+    health: ServiceHealth,
+    exit: bool,
+    restart: bool,
+}
+
+// Reads service transitions back out of the structured event fields (readiness, exits,
+// health checks, observe-only probes) and the restart history.
+fn health_changes(state: &RuntimeProcessState) -> Vec<HealthChange> {
+    let mut changes: Vec<HealthChange> = Vec::new();
+    for event in state.events.iter() {
+        if event.source == EventSource::Runtime && event.message == "Runtime stopped" {
+            for service in ["web", "backend", "mongo"] {
+                changes.push(HealthChange {
+                    ts_ms: event.ts_ms,
+                    service: service.to_string(),
+                    health: ServiceHealth::Stopped,
+                    exit: false,
+                    restart: false,
+                });
+            }
+            continue;
+        }
+        let Some(service) = event.fields.get("service").and_then(|value| value.as_str()) else {
+            continue;
+        };
+        let fields = &event.fields;
+        let (health, exit) = if fields.contains_key("clean") {
+            (ServiceHealth::Down, true)
+        } else if fields.contains_key("duration_ms") || fields.contains_key("healthy") {
+            (ServiceHealth::Up, false)
+        } else if fields.contains_key("latency_ms") {
+            (ServiceHealth::Unhealthy, false)
+        } else if let Some(ok) = fields.get("ok").and_then(|value| value.as_bool()) {
+            (if ok { ServiceHealth::Up } else { ServiceHealth::Down }, false)
+        } else {
+            continue;
+        };
+        changes.push(HealthChange {
+            ts_ms: event.ts_ms,
+            service: service.to_string(),
+            health,
+            exit,
+            restart: false,
+        });
+    }
+    for record in &state.restart_history {
+        changes.push(HealthChange {
+            ts_ms: record.ts_ms,
+            service: record.service.clone(),
+            health: if record.success { ServiceHealth::Up } else { ServiceHealth::Down },
+            exit: false,
+            restart: true,
+        });
+    }
+    changes.sort_by_key(|change| change.ts_ms);
+    changes
+}
+
+const DEFAULT_TIMELINE_BUCKET_MS: u64 = 60_000;
+const DEFAULT_TIMELINE_WINDOW_MS: u64 = 60 * 60_000;
+const MIN_TIMELINE_BUCKET_MS: u64 = 1_000;
+const MAX_TIMELINE_BUCKETS: u64 = 1_440;
+
+fn health_timeline(
+    state: &RuntimeProcessState,
+    bucket_ms: Option<u64>,
+    window_ms: Option<u64>,
+) -> Result<DesktopRuntimeHealthTimeline, String> {
+    let bucket_ms = bucket_ms.unwrap_or(DEFAULT_TIMELINE_BUCKET_MS);
+    let window_ms = window_ms.unwrap_or(DEFAULT_TIMELINE_WINDOW_MS);
+    if bucket_ms < MIN_TIMELINE_BUCKET_MS {
+        return Err(format!("bucket_ms must be at least {MIN_TIMELINE_BUCKET_MS}"));
+    }
+    let count = window_ms.div_ceil(bucket_ms).max(1);
+    if count > MAX_TIMELINE_BUCKETS {
+        return Err(format!(
+            "window_ms / bucket_ms gives {count} buckets; at most {MAX_TIMELINE_BUCKETS} are returned"
+        ));
+    }
+    let to_ms = now_ms();
+    let from_ms = to_ms.saturating_sub(count * bucket_ms);
+    let changes = health_changes(state);
+
+    let mut services: BTreeSet<String> = changes.iter().map(|change| change.service.clone()).collect();
+    if let Some(config) = state.launch_config.as_ref() {
+        services.insert("web".to_string());
+        if is_backend_required(config) {
+            services.insert("backend".to_string());
+        }
+        if is_mongo_required(config) {
+            services.insert("mongo".to_string());
+        }
+    }
+    let mut current: BTreeMap<String, ServiceHealth> =
+        services.iter().map(|service| (service.clone(), ServiceHealth::Unknown)).collect();
+    let mut pending = changes.iter().peekable();
+    // Replay what happened before the window so the first bucket starts from a known state.
+    while let Some(change) = pending.next_if(|change| change.ts_ms < from_ms) {
+        current.insert(change.service.clone(), change.health);
+    }
+
+    let mut buckets: Vec<DesktopRuntimeHealthBucket> = Vec::with_capacity(count as usize);
+    for index in 0..count {
+        let start_ms = from_ms + index * bucket_ms;
+        let end_ms = start_ms + bucket_ms;
+        let mut samples: BTreeMap<String, DesktopRuntimeServiceSample> = BTreeMap::new();
+        let mut worst = current.clone();
+        while let Some(change) = pending.next_if(|change| change.ts_ms < end_ms) {
+            current.insert(change.service.clone(), change.health);
+            let held = worst.entry(change.service.clone()).or_insert(ServiceHealth::Unknown);
+            *held = (*held).max(change.health);
+            let sample = samples.entry(change.service.clone()).or_default();
+            sample.exits += u32::from(change.exit);
+            sample.restarts += u32::from(change.restart);
+        }
+        for (service, health) in worst {
+            samples.entry(service).or_default().state = health.as_str().to_string();
+        }
+        buckets.push(DesktopRuntimeHealthBucket { start_ms, services: samples });
+    }
+    Ok(DesktopRuntimeHealthTimeline {
+        from_ms,
+        to_ms,
+        bucket_ms,
+        buckets,
+    })
+}
+
+// Resolves paths the way `start` does (same data dir, session scope and profile overrides) but
+// creates, migrates and switches nothing.
+fn runtime_paths(state: &RuntimeProcessState, profile_path: Option<String>) -> DesktopRuntimePaths {
+    let display = |path: &Path| path.display().to_string();
+    if let Some(config) = state.launch_config.as_ref() {
+        let runtime_dir = config.log_dir.parent().map(Path::to_path_buf).unwrap_or_default();
+        return DesktopRuntimePaths {
+            started: true,
+            data_dir: config.data_dir.clone(),
+            runtime_dir: display(&runtime_dir),
+            diagnostics_path: display(
+                &state
+                    .diagnostics_path
+                    .clone()
+                    .unwrap_or_else(|| runtime_dir.join("runtime-events.json")),
+            ),
+            log_dir: display(&config.log_dir),
+            session_path: display(
+                &state
+                    .session_path
+                    .clone()
+                    .unwrap_or_else(|| runtime_dir.join("runtime-session.json")),
+            ),
+            mongo_dbpath: is_mongo_required(config)
+                .then(|| mongo_dbpath(config))
+                .flatten()
+                .map(|path| display(&path)),
+        };
+    }
+    let profile_path = profile_path
+        .or_else(|| env::var("RUNTIME_PROFILE_PATH").ok())
+        .unwrap_or_default();
+    let mut profile = load_runtime_profile(Some(&profile_path));
+    if let Some(data_dir) = state.data_dir_override.clone().or_else(env_data_dir) {
+        profile.data_dir = Some(data_dir);
+    }
+    let explicit_session_id = env::var("DESKTOP_SESSION_ID")
+        .ok()
+        .or(profile.session_id.clone())
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty());
+    let runtime_scope = runtime_scope_for(explicit_session_id.as_deref(), &profile_path);
+    let runtime_dir = scoped_runtime_dir(profile.data_dir.as_deref(), runtime_scope.as_deref());
+    let diagnostics_path = profile
+        .diagnostics_path
+        .as_deref()
+        .filter(|raw| !raw.trim().is_empty())
+        .map(expand_user_path)
+        .unwrap_or_else(|| runtime_dir.join("runtime-events.json"));
+    let mode_raw = env::var("APP_RUNTIME_MODE")
+        .ok()
+        .or(profile.mode.clone())
+        .unwrap_or_else(|| "local_fullstack".to_string());
+    let mongo_dbpath = (RuntimeMode::from_raw(&mode_raw) == RuntimeMode::LocalFullstack)
+        .then(|| {
+            profile
+                .mongo_dbpath
+                .as_deref()
+                .filter(|raw| !raw.trim().is_empty())
+                .map(expand_user_path)
+                .or_else(|| profile.data_dir.as_ref().map(|dir| Path::new(dir).join("mongo")))
+        })
+        .flatten();
+    DesktopRuntimePaths {
+        started: false,
+        data_dir: profile.data_dir.clone(),
+        runtime_dir: display(&runtime_dir),
+        diagnostics_path: display(&diagnostics_path),
+        log_dir: display(&runtime_dir.join("logs")),
+        session_path: display(&runtime_dir.join("runtime-session.json")),
+        mongo_dbpath: mongo_dbpath.map(|path| display(&path)),
+    }
+}
+
+// The flat layout's file when present, else the most recently written per-session file.
+fn inspect_diagnostics_path(data_dir: Option<&str>) -> PathBuf {
+    let runtime_dir = runtime_dir_for_data_dir(data_dir);
+    let flat = runtime_dir.join("runtime-events.json");
+    if flat.exists() {
+        return flat;
+    }
+    let newest = fs::read_dir(runtime_dir.join("sessions"))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path().join("runtime-events.json"))
+        .filter_map(|path| {
+            let modified = fs::metadata(&path).and_then(|meta| meta.modified()).ok()?;
+            Some((modified, path))
+        })
+        .max_by_key(|(modified, _)| *modified);
+    newest.map(|(_, path)| path).unwrap_or(flat)
+}
+
+fn read_log_chunk(path: &Path, from_offset: u64, max_bytes: usize) -> Result<(u64, bool, Vec<u8>), String> {
+    let mut file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok((0, from_offset > 0, Vec::new())),
+        Err(err) => return Err(format!("failed to open {}: {err}", path.display())),
+    };
+    let len = file.metadata().map(|meta| meta.len()).unwrap_or(0);
+    // A file shorter than the caller's offset was rotated or truncated; restart from 0.
+    let (start, reset) = if from_offset > len { (0, true) } else { (from_offset, false) };
+    file.seek(SeekFrom::Start(start))
+        .map_err(|err| format!("failed to seek {}: {err}", path.display()))?;
+    let mut data: Vec<u8> = Vec::new();
+    file.take(max_bytes as u64)
+        .read_to_end(&mut data)
+        .map_err(|err| format!("failed to read {}: {err}", path.display()))?;
+    Ok((start, reset, data))
+}
+
+// Tells a log file apart from the one it replaced, which can be just as large.
+fn log_file_id(path: &Path) -> String {
+    let Ok(meta) = fs::metadata(path) else {
+        return String::new();
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        format!("{}:{}", meta.dev(), meta.ino())
+    }
+    #[cfg(not(unix))]
+    {
+        meta.created()
+            .ok()
+            .and_then(|created| created.duration_since(UNIX_EPOCH).ok())
+            .map(|since| since.as_nanos().to_string())
+            .unwrap_or_default()
+    }
+}
+
+// Bytes at the end of `data` that start a UTF-8 character without finishing it.
+fn incomplete_utf8_suffix(data: &[u8]) -> usize {
+    for back in 1..=data.len().min(3) {
+        let byte = data[data.len() - back];
+        if byte & 0xC0 == 0x80 {
+            continue;
+        }
+        let width = match byte {
+            0xC0..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF7 => 4,
+            _ => 1,
+        };
+        return if width > back { back } else { 0 };
+    }
+    0
+}
+
+const SERVICE_SIGNALS: [&str; 5] = ["HUP", "USR1", "USR2", "TERM", "INT"];
+
+#[cfg(unix)]
+fn deliver_service_signal(pid: u32, name: &str) -> Result<(), String> {
+    let signum = match name {
+        "HUP" => libc::SIGHUP,
+        "USR1" => libc::SIGUSR1,
+        "USR2" => libc::SIGUSR2,
+        "TERM" => libc::SIGTERM,
+        "INT" => libc::SIGINT,
+        other => return Err(format!("unsupported signal {other:?}")),
+    };
+    send_signal(pid, signum).map_err(|err| err.to_string())
+}
+
+#[cfg(not(unix))]
+fn deliver_service_signal(_pid: u32, _name: &str) -> Result<(), String> {
+    Err("signals are not supported on this platform".to_string())
+}
+
+const DEFAULT_TASK_TIMEOUT_MS: u64 = 10 * 60 * 1000;
+const TASK_EVENT_LINES: usize = 40;
+
+// `on_ready_command` runs on its own thread so neither the start nor the watchdog waits for
+// it, and its outcome is only logged. A trigger while the previous run is still going is
+// skipped.
+fn spawn_on_ready_hook(shared: &Arc<Mutex<RuntimeProcessState>>, state: &mut RuntimeProcessState, trigger: &str) {
+    let Some(config) = state.launch_config.clone() else {
+        return;
+    };
+    let Some(hook) = config.on_ready.clone() else {
+        return;
+    };
+    if state.on_ready_running {
+        push_runtime_event(
+            state,
+            "warn",
+            "task",
+            format!("on_ready_command still running; not started again after {trigger}"),
+        );
+        return;
+    }
+    let mut command = backend_env_command(&config, &hook.program);
+    command
+        .args(&hook.args)
+        .env("PQA_READY_TRIGGER", trigger)
+        .env("PQA_WEB_PORT", state.web_port.to_string())
+        .env("PQA_BACKEND_PORT", state.backend_port.to_string())
+        .env("PQA_BACKEND_URL", &state.backend_url);
+    if is_mongo_required(&config) {
+        command.env("PQA_MONGO_PORT", state.mongo_port.to_string());
+    }
+    let preview = describe_command("on_ready", &command);
+    let description = std::iter::once(preview.program).chain(preview.args).collect::<Vec<_>>().join(" ");
+    push_runtime_event_with(
+        state,
+        "info",
+        "task",
+        format!("Running on_ready_command after {trigger}: {description}"),
+        event_fields([("trigger", trigger.into())]),
+    );
+    state.on_ready_running = true;
+    let worker = shared.clone();
+    let trigger = trigger.to_string();
+    let spawned = std::thread::Builder::new().name("runtime-on-ready".to_string()).spawn(move || {
+        let started = Instant::now();
+        let result = run_command_with_timeout(&mut command, hook.timeout);
+        let duration_ms = started.elapsed().as_millis() as u64;
+        let mut state = lock_runtime(&worker);
+        state.on_ready_running = false;
+        let fields = |exit_code: Option<i32>| {
+            event_fields([
+                ("trigger", trigger.as_str().into()),
+                ("exit_code", exit_code.map_or(serde_json::Value::Null, serde_json::Value::from)),
+                ("duration_ms", duration_ms.into()),
+            ])
+        };
+        match result {
+            Ok((exit_code, output)) => {
+                let lines: Vec<&str> = output.lines().collect();
+                for line in &lines[lines.len().saturating_sub(TASK_EVENT_LINES)..] {
+                    push_runtime_event(&mut state, "debug", "task", format!("[on_ready] {line}"));
+                }
+                let (level, verb) = if exit_code == 0 { ("info", "finished") } else { ("warn", "failed") };
+                push_runtime_event_with(
+                    &mut state,
+                    level,
+                    "task",
+                    format!("on_ready_command {verb} with exit code {exit_code} in {duration_ms}ms"),
+                    fields(Some(exit_code)),
+                );
+            }
+            Err(err) => {
+                let message = format!("on_ready_command failed: {err}");
+                push_runtime_event_with(&mut state, "warn", "task", message, fields(None));
+            }
+        }
+    });
+    if let Err(err) = spawned {
+        state.on_ready_running = false;
+        push_runtime_event(state, "warn", "task", format!("on_ready_command not run: {err}"));
+    }
+}
+
+const DEFAULT_BACKEND_LOG_PATH: &str = "/debug/logs";
+const BACKEND_LOGS_TIMEOUT: Duration = Duration::from_secs(5);
+
+const DEFAULT_DRAIN_TIMEOUT_MS: u64 = 30_000;
+const MAX_DRAIN_TIMEOUT_MS: u64 = 10 * 60 * 1000;
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+// `POST <path>` asks the backend to stop accepting work; `GET <path>` then reports
+// `{"idle": bool}` until in-flight requests are done. Returns (level, outcome, message).
+fn drain_backend(url: &str, timeout: Duration) -> (&'static str, &'static str, String) {
+    if let Some(reason) = https_unsupported(url, "draining") {
+        return ("warn", "unsupported", format!("{reason}; stopping immediately"));
+    }
+    let started = Instant::now();
+    let response = match http_post(url, "{}", BACKEND_LOGS_TIMEOUT) {
+        Ok(response) => response,
+        Err(err) => return ("warn", "error", format!("POST {url} failed ({err}); stopping immediately")),
+    };
+    if matches!(response.status, 404 | 405) {
+        return (
+            "warn",
+            "absent",
+            format!("backend has no drain endpoint at {url} (HTTP {}); stopping immediately", response.status),
+        );
+    }
+    if !(200..300).contains(&response.status) {
+        return (
+            "warn",
+            "error",
+            format!("POST {url} returned HTTP {}; stopping immediately", response.status),
+        );
+    }
+    let mut body = response.body;
+    loop {
+        let idle = serde_json::from_str::<serde_json::Value>(&body)
+            .ok()
+            .and_then(|value| value.get("idle").and_then(|idle| idle.as_bool()))
+            .unwrap_or(false);
+        if idle {
+            return (
+                "info",
+                "drained",
+                format!("backend drained in {}ms", started.elapsed().as_millis()),
+            );
+        }
+        if started.elapsed() >= timeout {
+            return (
+                "warn",
+                "timeout",
+                format!("backend still busy after {}ms; stopping anyway", timeout.as_millis()),
+            );
+        }
+        std::thread::sleep(DRAIN_POLL_INTERVAL);
+        body = match http_get(url, BACKEND_LOGS_TIMEOUT) {
+            Ok(response) if (200..300).contains(&response.status) => response.body,
+            Ok(_) | Err(_) => String::new(),
+        };
+    }
+}
+
+fn ensure_dir_writable(dir: &Path) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|err| format!("cannot create {}: {err}", dir.display()))?;
+    let probe = dir.join(format!(".write-test-{}", now_ms()));
+    fs::write(&probe, b"ok").map_err(|err| format!("{} is not writable: {err}", dir.display()))?;
+    let _ = fs::remove_file(&probe);
+    Ok(())
+}
+
+const START_MAX_ATTEMPTS_CAP: u32 = 5;
+const SHUTDOWN_INTERRUPTED: &str = "start interrupted: the shell is shutting down";
+const START_RETRY_DELAY: Duration = Duration::from_millis(2_000);
+
+// Resets per-launch bookkeeping; runs before every start attempt since a failed attempt's
+// stop_all clears it.
+fn arm_launch_state(guard: &mut RuntimeProcessState, launch: &RuntimeLaunchConfig, session_path: PathBuf) {
+    guard.launch_generation = guard.launch_generation.wrapping_add(1);
+    guard.launch_config = Some(launch.clone());
+    guard.auto_restart = true;
+    guard.restart_count = 0;
+    guard.last_restart_ms = None;
+    guard.auto_restart_disabled_ms = None;
+    guard.failed_restart_ms.clear();
+    guard.escalated_restart_ms = None;
+    guard.parked_services.clear();
+    guard.unhealthy_streaks.clear();
+    guard.unhealthy_restart_count = 0;
+    guard.last_health_check_ms = None;
+    guard.session_path = Some(session_path);
+    restore_restart_budget(guard);
+    guard.port_adjusted = false;
+    guard.ready_durations_ms.clear();
+    guard.watch_fingerprint = None;
+    guard.watch_pending_since_ms = None;
+}
+
+// Also refreshes the cached status so `status(Some(false))` can show a determinate
+// progress bar while start still holds the runtime lock.
+fn report_start_progress(
+    on_progress: &dyn Fn(&DesktopRuntimeStartProgress),
+    controller: &RuntimeController,
+    state: &mut RuntimeProcessState,
+    phase: &str,
+    progress: f32,
+) {
+    state.start_phase = Some(phase.to_string());
+    state.start_progress = progress;
+    controller.remember_status(snapshot_status(state));
+    on_progress(&DesktopRuntimeStartProgress {
+        phase: phase.to_string(),
+        progress,
+        ts_ms: now_ms(),
+    });
+}
+
+// Spawns the sidecars in dependency order and waits for each to come up before the next,
+// so the web sidecar is launched with the backend port that is actually listening.
+fn launch_services(
+    on_progress: &dyn Fn(&DesktopRuntimeStartProgress),
+    controller: &RuntimeController,
+    guard: &mut RuntimeProcessState,
+    launch: &mut RuntimeLaunchConfig,
+    partial_start_ok: bool,
+) -> Result<Vec<String>, String> {
+    let mut unready: Vec<String> = Vec::new();
+    guard.mongo_dbpath = None;
+    guard.mongo_fresh = false;
+    if is_mongo_required(launch) && launch.adopt_existing_mongo && port_accepts(launch.mongo_port) {
+        guard.mongo = None;
+        guard.adopted_services.insert("mongo");
+        push_runtime_event(
+            guard,
+            "info",
+            "mongo",
+            format!("mongo is already listening on port {}; adopting it instead of spawning", launch.mongo_port),
+        );
+        if launch.mongo_auth.is_some() {
+            let outcome = bootstrap_mongo_auth(launch)?;
+            push_runtime_event(guard, "info", "mongo", format!("Mongo auth: {outcome}"));
+        }
+        report_start_progress(on_progress, controller, guard, "mongo ready", 0.25);
+    } else if is_mongo_required(launch) {
+        // A missing or empty dbpath means mongod initializes a brand-new database.
+        if let Some(db_dir) = mongo_dbpath(launch) {
+            guard.mongo_fresh = fs::read_dir(&db_dir)
+                .map(|mut entries| entries.next().is_none())
+                .unwrap_or(true);
+            guard.mongo_dbpath = Some(db_dir);
+        }
+        let mut mongo_log_offset = log_len(&service_log_path(&launch.log_dir, "mongo"));
+        let mongo = spawn_mongo(launch, &mut retry_logger(guard, "mongo"))?;
+        guard.mongo = mongo;
+        record_launched_command(guard, launch, "mongo");
+        if guard.mongo_fresh {
+            let dbpath = guard
+                .mongo_dbpath
+                .as_ref()
+                .map(|path| path.display().to_string())
+                .unwrap_or_default();
+            push_runtime_event(guard, "info", "mongo", format!("Initializing a new database at {dbpath}"));
+        }
+        report_start_progress(on_progress, controller, guard, "mongo spawned", 0.1);
+        let waited = Instant::now();
+        let needed = launch.readiness_consecutive_ok;
+        let mut mongo_ready = wait_for_mongo(guard, launch.mongo_port, Duration::from_secs(30), needed);
+        let mongo_exited = matches!(guard.mongo.as_mut().map(|child| child.try_wait()), Some(Ok(Some(_))));
+        if mongo_ready.is_err()
+            && mongo_exited
+            && launch.mongo_lock_recovery != MongoLockRecovery::Off
+            && is_mongo_lock_failure(&mongo_log_tail(launch, mongo_log_offset))
+        {
+            match recover_mongo_lock(launch) {
+                Ok(action) => {
+                    push_runtime_event_with(
+                        guard,
+                        "warn",
+                        "mongo",
+                        format!("mongod refused its dbpath lock; {action}; retrying mongo once"),
+                        event_fields([("service", "mongo".into()), ("recovery", action.as_str().into())]),
+                    );
+                    mongo_log_offset = log_len(&service_log_path(&launch.log_dir, "mongo"));
+                    let mongo = spawn_mongo(launch, &mut retry_logger(guard, "mongo"))?;
+                    guard.mongo = mongo;
+                    mongo_ready = wait_for_mongo(guard, launch.mongo_port, Duration::from_secs(30), needed);
+                }
+                Err(err) => push_runtime_event(
+                    guard,
+                    "error",
+                    "mongo",
+                    format!("mongod refused its dbpath lock; not recovered: {err}"),
+                ),
+            }
+        }
+        let mongo_unready = match mongo_ready {
+            Ok(probes) => {
+                let foreign = foreign_listener(guard, "mongo", launch.mongo_port);
+                if foreign.is_none() {
+                    log_service_ready(guard, "mongo", launch.mongo_port, waited, probes);
+                }
+                foreign
+            }
+            Err(reason) => Some(reason),
+        };
+        if let Some(reason) = mongo_unready.as_ref() {
+            // mongod usually logs exactly why it won't start (dbpath permissions, version mismatch).
+            let tail = mongo_log_tail(launch, mongo_log_offset);
+            let detail = if tail.is_empty() { String::new() } else { format!(": {}", tail.join(" | ")) };
+            let message = format!("{reason}{detail}");
+            if launch.mongo_auth.is_some() {
+                return Err(format!("mongo did not become ready for auth bootstrap: {message}"));
+            }
+            push_runtime_event_with(
+                guard,
+                "warn",
+                "mongo",
+                message,
+                event_fields([
+                    ("service", "mongo".into()),
+                    ("log_tail", serde_json::Value::from(tail)),
+                ]),
+            );
+        } else if launch.mongo_auth.is_some() {
+            let outcome = bootstrap_mongo_auth(launch)?;
+            push_runtime_event(guard, "info", "mongo", format!("Mongo auth: {outcome}"));
+        }
+        report_start_progress(on_progress, controller, guard, "mongo ready", 0.25);
+    } else {
+        guard.mongo = None;
+    }
+
+    if is_backend_required(launch) {
+        if let Some(debug) = launch.backend_debug.as_ref() {
+            let waiting = if debug.wait_for_client { "; the backend waits for a client before starting" } else { "" };
+            push_runtime_event_with(
+                guard,
+                "info",
+                "backend",
+                format!(
+                    "Backend debugger (debugpy) listening on {LOCAL_BIND_HOST}:{}{waiting}; attach a remote debugger there",
+                    debug.port
+                ),
+                event_fields([("service", "backend".into()), ("debug_port", debug.port.into())]),
+            );
+        }
+        let backend_log_offset = log_len(&service_log_path(&launch.log_dir, "backend"));
+        let configured = launch.backend_port;
+        let configured_busy = port_accepts(configured);
+        let backend = spawn_backend(launch, &mut retry_logger(guard, "backend"))?;
+        guard.backend = backend;
+        record_launched_command(guard, launch, "backend");
+        report_start_progress(on_progress, controller, guard, "backend spawned", 0.35);
+        let waited = Instant::now();
+        let timeout = service_ready_timeout(launch, "backend", Duration::from_secs(35));
+        let (offset, backend) = (backend_log_offset, guard.backend.as_mut());
+        let ready = wait_for_service_port(launch, "backend", configured, configured_busy, offset, timeout, backend)
+            .and_then(|(actual, probes)| {
+                foreign_listener(guard, "backend", actual).map_or(Ok((actual, probes)), Err)
+            });
+        match ready {
+            Ok((actual, probes)) => {
+                log_service_ready(guard, "backend", actual, waited, probes);
+                adopt_ready_port(guard, launch, "backend", configured, actual);
+                report_start_progress(on_progress, controller, guard, "backend ready", 0.6);
+            }
+            Err(reason) if partial_start_ok => defer_unready_service(guard, "backend", reason, &mut unready),
+            Err(reason) => return Err(reason),
+        }
+        launch_backend_replicas(guard, launch, partial_start_ok, &mut unready)?;
+    } else {
+        guard.backend = None;
+        guard.backend_replicas.clear();
+    }
+
+    let web_log_offset = log_len(&service_log_path(&launch.log_dir, "web"));
+    let configured = launch.web_port;
+    let configured_busy = port_accepts(configured);
+    let web = spawn_web(launch, &mut retry_logger(guard, "web"))?;
+    guard.web = Some(web);
+    record_launched_command(guard, launch, "web");
+    report_start_progress(on_progress, controller, guard, "web spawned", 0.7);
+    let waited = Instant::now();
+    let timeout = Duration::from_secs(35);
+    let web = guard.web.as_mut();
+    let ready = wait_for_service_port(launch, "web", configured, configured_busy, web_log_offset, timeout, web)
+        .and_then(|(actual, probes)| foreign_listener(guard, "web", actual).map_or(Ok((actual, probes)), Err));
+    match ready {
+        Ok((actual, probes)) => {
+            log_service_ready(guard, "web", actual, waited, probes);
+            adopt_ready_port(guard, launch, "web", configured, actual);
+            report_start_progress(on_progress, controller, guard, "web ready", 1.0);
+        }
+        Err(reason) if partial_start_ok => defer_unready_service(guard, "web", reason, &mut unready),
+        Err(reason) => return Err(reason),
+    }
+    Ok(unready)
+}
+
+// The extra replicas are all spawned first and then waited for together, so N replicas take
+// about as long to come up as one.
+fn launch_backend_replicas(
+    guard: &mut RuntimeProcessState,
+    launch: &RuntimeLaunchConfig,
+    partial_start_ok: bool,
+    unready: &mut Vec<String>,
+) -> Result<(), String> {
+    let services = backend_replica_services(launch);
+    guard.backend_replicas = services.iter().map(|_| None).collect();
+    let mut offsets: Vec<u64> = Vec::with_capacity(services.len());
+    let mut busy: Vec<bool> = Vec::with_capacity(services.len());
+    for (index, service) in services.iter().enumerate() {
+        offsets.push(log_len(&service_log_path(&launch.log_dir, service)));
+        busy.push(launch.backend_replica_ports.get(index).is_some_and(|port| port_accepts(*port)));
+        let child = spawn_backend_replica(launch, index, &mut retry_logger(guard, service))?;
+        guard.backend_replicas[index] = child;
+        record_launched_command(guard, launch, service);
+    }
+    let waited = Instant::now();
+    let timeout = Duration::from_secs(35);
+    let streak_window = ReadyStreak::new(launch.readiness_consecutive_ok).min_window();
+    let replicas = services.iter().zip(&launch.backend_replica_ports).zip(offsets.into_iter().zip(busy));
+    for (index, ((service, port), (offset, port_busy))) in replicas.enumerate() {
+        // The earlier replicas may have used up the timeout; one that is already up still
+        // gets the time to show a full streak.
+        let remaining = timeout.saturating_sub(waited.elapsed()).max(streak_window);
+        let child = guard.backend_replicas.get_mut(index).and_then(Option::as_mut);
+        let ready = match wait_for_service_port(launch, service, *port, port_busy, offset, remaining, child) {
+            Ok((actual, probes)) if actual == *port => {
+                foreign_listener(guard, service, *port).map_or(Ok(probes), Err)
+            }
+            Ok(_) => Err(format!("{service} did not become ready on port {port} in time")),
+            Err(reason) => Err(reason),
+        };
+        match ready {
+            Ok(probes) => log_service_ready(guard, service, *port, waited, probes),
+            Err(reason) if partial_start_ok => defer_unready_service(guard, service, reason, unready),
+            Err(reason) => return Err(reason),
+        }
+    }
+    Ok(())
+}
+
+// partial_start_ok: the unready sidecar is stopped and its slot left empty, so the watchdog
+// respawns it as a missing service while the ready ones keep running.
+fn defer_unready_service(
+    guard: &mut RuntimeProcessState,
+    service: &'static str,
+    reason: String,
+    unready: &mut Vec<String>,
+) {
+    if let Some(slot) = service_slot(guard, service) {
+        stop_child(slot);
+    }
+    push_runtime_event_with(
+        guard,
+        "warn",
+        service,
+        format!("{reason}; leaving it to the watchdog"),
+        event_fields([("service", service.into())]),
+    );
+    unready.push(reason);
+}
+
+const LISTENER_LOOKUP_TIMEOUT: Duration = Duration::from_secs(5);
+
+// PIDs listening on a local TCP port: `lsof` on unix, `netstat -ano` on Windows.
+fn listening_pids(port: u16) -> Result<Vec<u32>, String> {
+    let parse_pids = |output: &str| -> Vec<u32> {
+        let mut pids: Vec<u32> = output.lines().filter_map(|line| line.trim().parse().ok()).collect();
+        pids.sort_unstable();
+        pids.dedup();
+        pids
+    };
+    if cfg!(windows) {
+        let (_, output) = run_command_with_timeout(
+            Command::new("netstat").args(["-ano", "-p", "TCP"]),
+            LISTENER_LOOKUP_TIMEOUT,
+        )?;
+        let suffix = format!(":{port}");
+        let listeners: Vec<&str> = output
+            .lines()
+            .filter_map(|line| match line.split_whitespace().collect::<Vec<_>>()[..] {
+                [_, local, _, "LISTENING", pid] if local.ends_with(&suffix) => Some(pid),
+                _ => None,
+            })
+            .collect();
+        return Ok(parse_pids(&listeners.join("\n")));
+    }
+    // lsof exits 1 when nothing matches, which is not an error here.
+    let (_, output) = run_command_with_timeout(
+        Command::new("lsof").args(["-nP", &format!("-iTCP:{port}"), "-sTCP:LISTEN", "-t"]),
+        LISTENER_LOOKUP_TIMEOUT,
+    )?;
+    Ok(parse_pids(&output))
+}
+
+// None when the parent cannot be looked up (always on Windows, which has no `ps`).
+fn parent_pid(pid: u32) -> Option<u32> {
+    if cfg!(windows) {
+        return None;
+    }
+    match run_command_with_timeout(
+        Command::new("ps").args(["-o", "ppid=", "-p", &pid.to_string()]),
+        LISTENER_LOOKUP_TIMEOUT,
+    ) {
+        Ok((0, output)) => output.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+// Whether `pid` is `root` or one of its descendants (node under npm, a uvicorn worker).
+fn spawned_by(pid: u32, root: u32) -> Option<bool> {
+    let mut current = pid;
+    for _ in 0..16 {
+        if current == root {
+            return Some(true);
+        }
+        match parent_pid(current)? {
+            0 | 1 => return Some(false),
+            parent => current = parent,
+        }
+    }
+    Some(false)
+}
+
+// The port answering after a spawn is not necessarily ours: an orphaned sidecar from an
+// earlier run may still hold it. Returns the failure reason when the listener is foreign;
+// a listener that cannot be identified is logged and accepted.
+fn foreign_listener(guard: &mut RuntimeProcessState, service: &'static str, port: u16) -> Option<String> {
+    let child_pid = service_slot(guard, service).and_then(|slot| slot.as_ref().map(Child::id))?;
+    let found = listening_pids(port)
+        .and_then(|pids| if pids.is_empty() { Err("no listener found".to_string()) } else { Ok(pids) });
+    let pids = match found {
+        Ok(pids) => pids,
+        Err(err) => {
+            push_runtime_event(guard, "debug", service, format!("Cannot verify who listens on port {port}: {err}"));
+            return None;
+        }
+    };
+    let mut verified = true;
+    for pid in &pids {
+        match spawned_by(*pid, child_pid) {
+            Some(true) => return None,
+            Some(false) => {}
+            None => verified = false,
+        }
+    }
+    if !verified {
+        push_runtime_event(
+            guard,
+            "debug",
+            service,
+            format!("Cannot verify that pid {pids:?} on port {port} belongs to {service} (pid {child_pid})"),
+        );
+        return None;
+    }
+    let listeners = pids.iter().map(u32::to_string).collect::<Vec<_>>().join(", ");
+    let message = format!(
+        "{service} port {port} is held by pid {listeners}, not the spawned {service} (pid {child_pid}); likely a stale {service} from an earlier run"
+    );
+    push_runtime_event_with(
+        guard,
+        "error",
+        service,
+        message.clone(),
+        event_fields([
+            ("service", service.into()),
+            ("port", port.into()),
+            ("pid", child_pid.into()),
+            ("listener_pids", serde_json::Value::from(pids)),
+        ]),
+    );
+    Some(message)
+}
+
+fn log_service_ready(guard: &mut RuntimeProcessState, service: &'static str, port: u16, waited: Instant, probes: u32) {
+    let duration_ms = waited.elapsed().as_millis() as u64;
+    guard.ready_durations_ms.insert(service, duration_ms);
+    push_runtime_event_with(
+        guard,
+        "debug",
+        service,
+        format!("{service} ready on port {port} after {duration_ms}ms"),
+        event_fields([
+            ("service", service.into()),
+            ("port", port.into()),
+            ("duration_ms", duration_ms.into()),
+            ("probes", probes.into()),
+        ]),
+    );
+}
+
+fn adopt_ready_port(
+    guard: &mut RuntimeProcessState,
+    launch: &mut RuntimeLaunchConfig,
+    service: &str,
+    configured: u16,
+    actual: u16,
+) {
+    if actual == configured {
+        return;
+    }
+    set_service_port(launch, service, actual);
+    guard.port_adjusted = true;
+    guard.launch_config = Some(launch.clone());
+    push_runtime_event(
+        guard,
+        "warn",
+        "runtime",
+        format!("{service} is listening on port {actual} instead of configured {configured}"),
+    );
+}
+
+// Tauri-free lifecycle API; the desktop shell's commands are thin wrappers over these, and
+// a headless consumer (e.g. a CI runner) can drive the same runtime without a webview.
+impl RuntimeController {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Starts the background watchdog thread (liveness, health checks, watch mode).
+    pub fn spawn_watchdog(&self) {
+        spawn_runtime_watchdog(self.state.clone());
+    }
+
+    // Makes `dir` the default data dir when none is configured. Only the first call takes
+    // effect; call it before the first command so events are not loaded from the old default.
+    pub fn use_app_data_dir(&self, dir: PathBuf) {
+        if APP_DATA_DIR.set(dir).is_ok() {
+            let mut guard = lock_runtime(&self.state);
+            if guard.launch_config.is_none() && !guard.diagnostics_path_explicit {
+                guard.diagnostics_path = None;
+            }
+        }
+    }
+
+    // Unix only: SIGTERM, SIGINT and SIGHUP stop the sidecars before the process exits.
+    pub fn install_signal_handlers(&self) {
+        #[cfg(unix)]
+        install_shutdown_signal_handlers(self.state.clone());
+    }
+
+    // One synchronous watchdog round, for consumers that drive their own loop instead
+    // of calling `spawn_watchdog`. With neither, exited sidecars are only reaped when a
+    // status or another command polls them.
+    pub fn watchdog_tick(&self) {
+        if lock_runtime(&self.state).observe_only {
+            run_observe_pass(&self.state, false);
+            return;
+        }
+        run_watchdog_pass(&self.state);
+        run_health_pass(&self.state);
+        run_watch_pass(&self.state);
+        run_log_budget_pass(&self.state);
+        run_config_drift_pass(&self.state);
+    }
+
+    pub fn reconcile(&self) -> DesktopRuntimeStatus {
+        let mut guard = lock_runtime(&self.state);
+        reconcile_runtime_state(&mut guard);
+        self.remember_status(snapshot_status(&guard))
+    }
+
+    pub fn health(&self) -> DesktopRuntimeHealth {
+        let (mode, plan) = {
+            let guard = lock_runtime(&self.state);
+            (guard.mode.as_str().to_string(), health_probe_plan(&guard))
+        };
+        let probes = run_health_probes(&plan);
+        {
+            let mut guard = lock_runtime(&self.state);
+            if guard.launch_config.is_some() {
+                remember_backend_health(&mut guard, &probes);
+            }
+        }
+        DesktopRuntimeHealth {
+            checked_at_ms: now_ms(),
+            mode,
+            healthy: probes.iter().all(|probe| probe.ok),
+            probes,
+        }
+    }
+
+    pub fn status(&self, reconcile: Option<bool>) -> DesktopRuntimeStatus {
+        // `reconcile: false` never waits: it snapshots if the lock is free, otherwise it returns
+        // the last status handed out (e.g. while a start is waiting for readiness).
+        if !reconcile.unwrap_or(true) {
+            return match self.state.try_lock() {
+                Ok(guard) => self.remember_status(snapshot_status(&guard)),
+                Err(_) => self.cached_status(),
+            };
+        }
+        let mut guard = self.state.lock().expect("desktop runtime mutex poisoned");
+        ensure_diagnostics_state(&mut guard, None);
+        reconcile_if_stale(&mut guard);
+        self.remember_status(snapshot_status(&guard))
+    }
+
+    pub fn diagnostics(&self, limit: Option<u32>, contains: Option<String>) -> DesktopRuntimeDiagnostics {
+        let mut guard = self.state.lock().expect("desktop runtime mutex poisoned");
+        ensure_diagnostics_state(&mut guard, None);
+        reconcile_if_stale(&mut guard);
+        let max = limit.unwrap_or(80).clamp(1, 300) as usize;
+        let needle = contains.map(|value| value.to_lowercase()).filter(|value| !value.is_empty());
+        let matching: Vec<&DesktopRuntimeDiagEvent> = guard
+            .events
+            .iter()
+            .filter(|event| needle.as_deref().is_none_or(|needle| event.message.to_lowercase().contains(needle)))
+            .collect();
+        let matched = matching.len();
+        let events = matching[matched.saturating_sub(max)..].iter().map(|event| (*event).clone()).collect();
+        DesktopRuntimeDiagnostics {
+            generated_at_ms: now_ms(),
+            status: snapshot_status(&guard),
+            events,
+            matched,
+        }
+    }
+
+    // The newest error event and its neighbours, for an error banner. Falls back to the
+    // status `last_error` and the latest events when no error event is recorded.
+    pub fn last_error(&self, around: Option<u32>) -> DesktopRuntimeLastError {
+        let mut guard = lock_runtime(&self.state);
+        ensure_diagnostics_state(&mut guard, None);
+        let around = around.unwrap_or(3).min(25) as usize;
+        let events: Vec<&DesktopRuntimeDiagEvent> = guard.events.iter().collect();
+        let Some(index) = events.iter().rposition(|event| event.level == EventLevel::Error) else {
+            return DesktopRuntimeLastError {
+                error: guard.last_error.clone(),
+                ts_ms: None,
+                context: if guard.last_error.is_some() {
+                    events[events.len().saturating_sub(around)..].iter().map(|event| (*event).clone()).collect()
+                } else {
+                    Vec::new()
+                },
+            };
+        };
+        let end = (index + around + 1).min(events.len());
+        DesktopRuntimeLastError {
+            error: Some(events[index].message.clone()),
+            ts_ms: Some(events[index].ts_ms),
+            context: events[index.saturating_sub(around)..end].iter().map(|event| (*event).clone()).collect(),
+        }
+    }
+
+    pub fn restart_history(&self, service: Option<String>, limit: Option<u32>) -> Vec<RestartRecord> {
+        let guard = self.state.lock().expect("desktop runtime mutex poisoned");
+        let service = service.map(|value| value.trim().to_lowercase()).filter(|value| !value.is_empty());
+        let records: Vec<RestartRecord> = guard
+            .restart_history
+            .iter()
+            .filter(|record| service.as_deref().map(|name| record.service == name).unwrap_or(true))
+            .cloned()
+            .collect();
+        let max = limit.unwrap_or(MAX_RESTART_HISTORY as u32).clamp(1, MAX_RESTART_HISTORY as u32) as usize;
+        records[records.len().saturating_sub(max)..].to_vec()
+    }
+
+    // Compares two exported event files (e.g. a passing and a failing QA run) kind by kind.
+    pub fn diff_diagnostics(&self, a_path: &str, b_path: &str) -> Result<DesktopRuntimeDiagnosticsDiff, String> {
+        let resolve = |raw: &str| {
+            let raw = raw.trim();
+            if raw.is_empty() {
+                Err("diagnostics path is empty".to_string())
+            } else {
+                Ok(expand_user_path(raw))
+            }
+        };
+        diff_diagnostics(&resolve(a_path)?, &resolve(b_path)?)
+    }
+
+    pub fn health_timeline(
+        &self,
+        bucket_ms: Option<u64>,
+        window_ms: Option<u64>,
+    ) -> Result<DesktopRuntimeHealthTimeline, String> {
+        let guard = lock_runtime(&self.state);
+        health_timeline(&guard, bucket_ms, window_ms)
+    }
+
+    // Read-only "view past run": swaps in another data dir's events without merging the current
+    // ones into it. Refused while a runtime is launched so live events keep their file.
+    pub fn load_diagnostics(&self, data_dir: Option<String>) -> Result<DesktopRuntimeDiagnostics, String> {
+        let mut guard = lock_runtime(&self.state);
+        if guard.launch_config.is_some() {
+            return Err("runtime is started; stop it before loading another data dir's diagnostics".to_string());
+        }
+        let data_dir = data_dir.filter(|value| !value.trim().is_empty());
+        let path = inspect_diagnostics_path(data_dir.as_deref());
+        if !path.exists() {
+            return Err(format!("no diagnostics found at {}", path.display()));
+        }
+        guard.events = load_runtime_events_from_path(&path);
+        if guard.persist_diagnostics {
+            guard.diagnostics_path = Some(path);
+        }
+        guard.diagnostics_path_explicit = false;
+        Ok(DesktopRuntimeDiagnostics {
+            generated_at_ms: now_ms(),
+            status: snapshot_status(&guard),
+            events: guard.events.to_vec(),
+            matched: guard.events.len(),
+        })
+    }
+
+    pub fn stop(&self, clear_diagnostics: Option<bool>) -> Result<DesktopRuntimeStatus, String> {
+        let mut guard = self
+            .state
+            .lock()
+            .map_err(|_| "desktop runtime mutex poisoned".to_string())?;
+        let observed = guard.observe_only;
+        if observed {
+            push_runtime_event(
+                &mut guard,
+                "info",
+                "runtime",
+                "Observe-only: nothing to stop; the observed stack keeps running and observing ended",
+            );
+        } else {
+            push_runtime_event(&mut guard, "info", "runtime", "Stop requested");
+        }
+        stop_all(&mut guard);
+        persist_runtime_session(&guard);
+        guard.last_error = None;
+        guard.last_reconcile_ms = None;
+        if !observed {
+            push_runtime_event(&mut guard, "info", "runtime", "Runtime stopped");
+        }
+        if clear_diagnostics.unwrap_or(false) && !guard.persist_diagnostics {
+            guard.events.clear();
+            push_runtime_event(&mut guard, "info", "runtime", "Diagnostics cleared on stop (kept in memory only)");
+        } else if clear_diagnostics.unwrap_or(false) {
+            push_runtime_event(
+                &mut guard,
+                "info",
+                "runtime",
+                "Archiving diagnostics on stop so the next session starts clean",
+            );
+            match rotate_diagnostics(&mut guard, Some("stop"), false) {
+                Ok(archived) => push_runtime_event(
+                    &mut guard,
+                    "info",
+                    "runtime",
+                    format!("Previous run's diagnostics archived to {}", archived.display()),
+                ),
+                Err(err) => push_runtime_event(
+                    &mut guard,
+                    "warn",
+                    "runtime",
+                    format!("Diagnostics kept: archiving on stop failed: {err}"),
+                ),
+            }
+        } else {
+            push_runtime_event(&mut guard, "debug", "runtime", "Diagnostics kept across stop");
+        }
+        Ok(self.remember_status(snapshot_status(&guard)))
+    }
+
+    pub fn tail_log(
+        &self,
+        service: &str,
+        from_offset: Option<u64>,
+        max_bytes: Option<u32>,
+        file_id: Option<String>,
+    ) -> Result<DesktopRuntimeLogChunk, String> {
+        let service = service.trim().to_lowercase();
+        if !matches!(service.as_str(), "web" | "backend" | "mongo") && backend_replica_index(&service).is_none() {
+            return Err(format!("unknown service: {service}"));
+        }
+        let log_dir = {
+            let guard = lock_runtime(&self.state);
+            guard
+                .launch_config
+                .as_ref()
+                .map(|config| config.log_dir.clone())
+                .unwrap_or_else(|| log_dir_for_data_dir(None))
+        };
+        let path = service_log_path(&log_dir, &service);
+        let max = max_bytes.unwrap_or(64 * 1024).clamp(4, 1024 * 1024) as usize;
+        // The size check in `read_log_chunk` misses a rotation to a file at least as large as the
+        // offset; the file id the caller got last time catches that.
+        let current_id = log_file_id(&path);
+        let replaced = file_id.is_some_and(|previous| !previous.is_empty() && previous != current_id);
+        let offset = if replaced { 0 } else { from_offset.unwrap_or(0) };
+        let (start, reset, mut data) = read_log_chunk(&path, offset, max)?;
+        // A character split at the end of the chunk is left for the next read.
+        let partial = incomplete_utf8_suffix(&data);
+        data.truncate(data.len() - partial);
+        Ok(DesktopRuntimeLogChunk {
+            service,
+            path: path.to_string_lossy().to_string(),
+            from_offset: start,
+            next_offset: start.saturating_add(data.len() as u64),
+            reset: reset || replaced,
+            file_id: current_id,
+            data: String::from_utf8_lossy(&data).to_string(),
+        })
+    }
+
+    // Runs a profile task against the live launch config (so it reaches the managed mongo);
+    // the runtime lock is released while the task runs.
+    pub fn run_task(
+        &self,
+        name: &str,
+        args: Option<Vec<String>>,
+    ) -> Result<DesktopRuntimeTaskResult, String> {
+        let name = name.trim().to_string();
+        let (config, task) = {
+            let guard = lock_runtime(&self.state);
+            let Some(config) = guard.launch_config.clone() else {
+                return Err("runtime is not started".to_string());
+            };
+            if guard.observe_only {
+                return Err("observe-only: tasks are not run against an observed stack".to_string());
+            }
+            let Some(task) = config.tasks.get(&name).cloned() else {
+                let known = config.tasks.keys().cloned().collect::<Vec<_>>().join(", ");
+                return Err(format!("unknown task {name:?} (configured: {known})"));
+            };
+            (config, task)
+        };
+        let program = task
+            .program
+            .clone()
+            .filter(|value| !value.trim().is_empty())
+            .unwrap_or_else(|| config.python_bin.clone());
+        let mut command = backend_env_command(&config, &program);
+        command.args(task.args.clone().unwrap_or_default());
+        command.args(args.unwrap_or_default());
+        let preview = describe_command("task", &command);
+        let description = std::iter::once(preview.program).chain(preview.args).collect::<Vec<_>>().join(" ");
+        {
+            let mut guard = lock_runtime(&self.state);
+            push_runtime_event(&mut guard, "info", "task", format!("Running task {name}: {description}"));
+        }
+        let started = Instant::now();
+        let timeout = Duration::from_millis(task.timeout_ms.unwrap_or(DEFAULT_TASK_TIMEOUT_MS).max(1));
+        let result = run_command_with_timeout(&mut command, timeout);
+        let duration_ms = started.elapsed().as_millis() as u64;
+        let mut guard = lock_runtime(&self.state);
+        let (exit_code, output) = match result {
+            Ok(done) => done,
+            Err(err) => {
+                push_runtime_event(&mut guard, "error", "task", format!("Task {name} failed: {err}"));
+                return Err(err);
+            }
+        };
+        let lines: Vec<&str> = output.lines().collect();
+        for line in &lines[lines.len().saturating_sub(TASK_EVENT_LINES)..] {
+            push_runtime_event(&mut guard, "debug", "task", format!("[{name}] {line}"));
+        }
+        let (level, verb) = if exit_code == 0 { ("info", "finished") } else { ("error", "failed") };
+        push_runtime_event(
+            &mut guard,
+            level,
+            "task",
+            format!("Task {name} {verb} with exit code {exit_code} in {duration_ms}ms"),
+        );
+        Ok(DesktopRuntimeTaskResult {
+            name,
+            exit_code,
+            duration_ms,
+            output,
+        })
+    }
+
+    // Works without a spawned backend (remote slim), so it only needs the resolved backend_url.
+    pub fn backend_logs(&self, lines: Option<u32>) -> Result<DesktopRuntimeBackendLogs, String> {
+        let (backend_url, log_path) = {
+            let guard = lock_runtime(&self.state);
+            let log_path = guard
+                .launch_config
+                .as_ref()
+                .map(|config| config.backend_log_path.clone())
+                .unwrap_or_else(|| DEFAULT_BACKEND_LOG_PATH.to_string());
+            (guard.backend_url.clone(), log_path)
+        };
+        let lines = lines.unwrap_or(200).clamp(1, 5_000);
+        let separator = if log_path.contains('?') { '&' } else { '?' };
+        let url = format!("{}{separator}lines={lines}", join_url_path(&backend_url, &log_path));
+        if let Some(reason) = https_unsupported(&url, "desktop_runtime_backend_logs") {
+            return Err(reason);
+        }
+        let response = http_get(&url, BACKEND_LOGS_TIMEOUT).map_err(|err| format!("GET {url} failed: {err}"))?;
+        if !(200..300).contains(&response.status) {
+            let snippet: String = response.body.chars().take(200).collect();
+            return Err(format!("GET {url} returned HTTP {}: {}", response.status, snippet.trim()));
+        }
+        Ok(DesktopRuntimeBackendLogs {
+            url,
+            status: response.status,
+            body: response.body,
+        })
+    }
+
+    // Asks a local backend to finish in-flight work before the normal stop; any problem
+    // with the drain endpoint falls back to stopping right away.
+    pub fn drain(&self, timeout_ms: Option<u64>) -> Result<DesktopRuntimeStatus, String> {
+        // The backend has no drain route of its own, so there is nothing to call unless the
+        // profile names one.
+        let (local, target) = {
+            let mut guard = lock_runtime(&self.state);
+            push_runtime_event(&mut guard, "info", "runtime", "Drain requested");
+            let local = guard
+                .launch_config
+                .as_ref()
+                .filter(|config| is_backend_required(config) && guard.backend.is_some());
+            let target = local.and_then(|config| config.backend_drain_path.as_deref());
+            (local.is_some(), target.map(|path| join_url_path(&guard.backend_url, path)))
+        };
+        let timeout = Duration::from_millis(timeout_ms.unwrap_or(DEFAULT_DRAIN_TIMEOUT_MS).min(MAX_DRAIN_TIMEOUT_MS));
+        let (level, outcome, message) = match target {
+            Some(url) => drain_backend(&url, timeout),
+            None if local => ("info", "disabled", "no backend_drain_path configured; stopping".to_string()),
+            None => ("info", "skipped", "no local backend to drain; stopping".to_string()),
+        };
+        {
+            let mut guard = lock_runtime(&self.state);
+            push_runtime_event_with(
+                &mut guard,
+                level,
+                "runtime",
+                format!("Drain: {message}"),
+                event_fields([("outcome", outcome.into())]),
+            );
+        }
+        self.stop(None)
+    }
+
+    pub fn rotate_diagnostics(
+        &self,
+        label: Option<String>,
+        keep_events: Option<bool>,
+    ) -> Result<String, String> {
+        let mut guard = lock_runtime(&self.state);
+        let archived = rotate_diagnostics(&mut guard, label.as_deref(), keep_events.unwrap_or(false))?;
+        let archived = archived.to_string_lossy().to_string();
+        push_runtime_event(&mut guard, "info", "runtime", format!("Diagnostics archived to {archived}"));
+        Ok(archived)
+    }
+
+    // Moves the runtime files (events, session file, future logs) to a new data dir and makes it
+    // the data dir for later starts. A running mongo keeps its dbpath until the next start, and
+    // running sidecars keep writing their current log files until they are respawned.
+    pub fn set_data_dir(&self, path: &str) -> Result<DesktopRuntimeStatus, String> {
+        let raw = path.trim().to_string();
+        if raw.is_empty() {
+            return Err("data dir path is empty".to_string());
+        }
+        let mut guard = lock_runtime(&self.state);
+        let runtime_dir = scoped_runtime_dir(Some(&raw), guard.runtime_scope.as_deref());
+        ensure_dir_writable(&runtime_dir)?;
+        let previous = guard
+            .diagnostics_path
+            .as_ref()
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(|| "none".to_string());
+        guard.data_dir_override = Some(raw.clone());
+        if !guard.diagnostics_path_explicit {
+            switch_diagnostics_path(&mut guard, runtime_dir.join("runtime-events.json"));
+        }
+        if let Some(config) = guard.launch_config.as_mut() {
+            config.log_dir = runtime_dir.join("logs");
+        }
+        if guard.session_path.is_some() {
+            guard.session_path = Some(runtime_dir.join("runtime-session.json"));
+            persist_runtime_session(&guard);
+        }
+        let events_note = if guard.diagnostics_path_explicit {
+            "events stay at the profile's diagnostics_path".to_string()
+        } else {
+            format!("events migrated from {previous}")
+        };
+        push_runtime_event(
+            &mut guard,
+            "info",
+            "runtime",
+            format!("Data dir changed to {}; {events_note}", expand_tilde_path(&raw).display()),
+        );
+        Ok(self.remember_status(snapshot_status(&guard)))
+    }
+
+    pub fn paths(&self, profile_path: Option<String>) -> DesktopRuntimePaths {
+        let guard = lock_runtime(&self.state);
+        runtime_paths(&guard, profile_path)
+    }
+
+    pub fn validate_profile(&self, path: &str) -> Result<DesktopRuntimeProfileValidation, String> {
+        validate_runtime_profile_file(path)
+    }
+
+    pub fn scan_ports(&self, start: u16, end: u16) -> Result<DesktopRuntimePortScan, String> {
+        scan_free_ports(start, end)
+    }
+
+    // Sends a Unix signal (e.g. HUP for a config reload) to a spawned sidecar. There is no
+    // signal equivalent on Windows, so there it is a logged no-op.
+    pub fn signal_service(&self, service: &str, signal: &str) -> Result<DesktopRuntimeSignalResult, String> {
+        let service = match service.trim().to_lowercase().as_str() {
+            "web" => "web",
+            "backend" => "backend",
+            "mongo" => "mongo",
+            other => return Err(format!("unknown service: {other}")),
+        };
+        let signal = signal.trim().trim_start_matches("SIG").trim_start_matches("sig").to_uppercase();
+        if !SERVICE_SIGNALS.contains(&signal.as_str()) {
+            return Err(format!("unsupported signal {signal:?} (expected one of {})", SERVICE_SIGNALS.join(", ")));
+        }
+        let mut guard = lock_runtime(&self.state);
+        let pid = match service_slot(&mut guard, service).and_then(|slot| slot.as_ref().map(|child| child.id())) {
+            Some(pid) => pid,
+            None if guard.adopted_services.contains(service) => {
+                return Err(format!("{service} was adopted, not started by this shell; it has no known pid"))
+            }
+            None => return Err(format!("{service} is not running")),
+        };
+        let (delivered, message) = if cfg!(windows) {
+            (false, format!("SIG{signal} not sent to {service} (pid {pid}): signals are not supported on Windows"))
+        } else {
+            deliver_service_signal(pid, &signal)
+                .map_err(|err| format!("SIG{signal} not sent to {service} (pid {pid}): {err}"))?;
+            (true, format!("Sent SIG{signal} to {service} (pid {pid})"))
+        };
+        push_runtime_event_with(
+            &mut guard,
+            if delivered { "info" } else { "warn" },
+            "runtime",
+            message.clone(),
+            event_fields([
+                ("service", service.into()),
+                ("signal", signal.as_str().into()),
+                ("pid", pid.into()),
+            ]),
+        );
+        Ok(DesktopRuntimeSignalResult {
+            service: service.to_string(),
+            signal,
+            pid,
+            delivered,
+            message,
+        })
+    }
+
+    pub fn retry_now(&self) -> Result<DesktopRuntimeStatus, String> {
+        retry_down_services(&self.state)?;
+        let guard = lock_runtime(&self.state);
+        Ok(self.remember_status(snapshot_status(&guard)))
+    }
+
+    pub fn restart_service(&self, service: &str) -> Result<DesktopRuntimeStatus, String> {
+        restart_service(&self.state, service, "requested")?;
+        let guard = lock_runtime(&self.state);
+        Ok(self.remember_status(snapshot_status(&guard)))
+    }
+
+    // Program, args, cwd and the (redacted) env set on top of the shell's own, for each
+    // sidecar that is up, in launch order.
+    pub fn commandlines(&self) -> Vec<DesktopRuntimeCommandPreview> {
+        let guard = lock_runtime(&self.state);
+        let Some(config) = guard.launch_config.as_ref() else {
+            return Vec::new();
+        };
+        with_backend_replicas(config, &["mongo", "backend", "web"])
+            .into_iter()
+            .filter(|service| service_present(&guard, service))
+            .filter_map(|service| guard.launched_commands.get(service).cloned())
+            .collect()
+    }
+
+    // The env `spawn_web` would set, for running the web app outside the shell. A started
+    // stack in the same mode answers with what its web sidecar got; otherwise the launch
+    // config is resolved from the profile like a start would, without spawning anything.
+    pub fn web_env(
+        &self,
+        mode: Option<String>,
+        profile_path: Option<String>,
+        web_dev: Option<bool>,
+    ) -> Result<DesktopRuntimeWebEnv, String> {
+        let requested = mode.as_deref().map(RuntimeMode::from_raw);
+        let data_dir_override = {
+            let guard = lock_runtime(&self.state);
+            let running = guard
+                .launch_config
+                .as_ref()
+                .filter(|_| !guard.observe_only && profile_path.is_none() && web_dev.is_none())
+                .filter(|config| requested.is_none_or(|mode| mode == config.mode));
+            if let Some(config) = running {
+                return Ok(web_env_preview(config, "running"));
+            }
+            guard.data_dir_override.clone()
+        };
+
+        let profile_path = profile_path
+            .or_else(|| env::var("RUNTIME_PROFILE_PATH").ok())
+            .unwrap_or_default();
+        let mut profile = load_runtime_profile(Some(&profile_path));
+        if let Some(data_dir) = data_dir_override.or_else(env_data_dir) {
+            profile.data_dir = Some(data_dir);
+        }
+        let mode = requested.unwrap_or_else(|| {
+            let raw = env::var("APP_RUNTIME_MODE")
+                .ok()
+                .or(profile.mode.clone())
+                .unwrap_or_else(|| "local_fullstack".to_string());
+            RuntimeMode::from_raw(&raw)
+        });
+        let web_dev = web_dev.unwrap_or(false);
+        // Port 0 gets a port that is free right now; a real start picks its own.
+        let ports = profile.local_ports.clone().unwrap_or_default();
+        let resolve_port = |configured: Option<u16>, default: u16| match configured.unwrap_or(default) {
+            0 => pick_free_port(),
+            port => Ok(port),
+        };
+        let web_port = if web_dev { WEB_DEV_PORT } else { resolve_port(ports.web, DEFAULT_WEB_PORT)? };
+        let backend_port = resolve_port(ports.backend, DEFAULT_BACKEND_PORT)?;
+        let mongo_port = resolve_port(ports.mongo, DEFAULT_MONGO_PORT)?;
+        let explicit_session_id = env::var("DESKTOP_SESSION_ID")
+            .ok()
+            .or(profile.session_id.clone())
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty());
+        let runtime_scope = runtime_scope_for(explicit_session_id.as_deref(), &profile_path);
+        let runtime_dir = scoped_runtime_dir(profile.data_dir.as_deref(), runtime_scope.as_deref());
+        let workspace_root = resolve_workspace_root(None)?;
+        // Only the env is needed, so the config is built the way an observe-only start builds
+        // it: without requiring the sidecar binaries.
+        let launch = RuntimeLaunchConfigBuilder::new(mode, &workspace_root, &runtime_dir)
+            .ports(web_port, backend_port, mongo_port)
+            .session(explicit_session_id.unwrap_or_else(|| format!("desktop-{}", now_ms())), None)
+            .runtime_profile_path(&profile_path)
+            .web_dev(web_dev)
+            .profile(&profile)
+            .observe_only(true)
+            .build()?;
+        Ok(web_env_preview(&launch, "profile"))
+    }
+
+    // Holds off the watchdog's restarts (crash and health based) without touching
+    // `auto_restart` or the launch config, e.g. while the backend sits at a breakpoint.
+    pub fn suspend_watchdog(&self, suspended: bool) -> DesktopRuntimeStatus {
+        let mut guard = lock_runtime(&self.state);
+        if guard.watchdog_suspended != suspended {
+            guard.watchdog_suspended = suspended;
+            let message = if suspended {
+                "Watchdog suspended: exits are recorded but not restarted"
+            } else {
+                // Failures seen while paused say nothing about the resumed process.
+                guard.unhealthy_streaks.clear();
+                guard.last_health_check_ms = None;
+                "Watchdog resumed"
+            };
+            push_runtime_event_with(
+                &mut guard,
+                "info",
+                "watchdog",
+                message.to_string(),
+                event_fields([("suspended", suspended.into())]),
+            );
+        }
+        self.remember_status(snapshot_status(&guard))
+    }
+
+    // Arms the launch state without spawning anything; the watchdog then only probes the
+    // configured endpoints, and stop leaves the observed stack alone.
+    fn start_observing(
+        &self,
+        mut guard: MutexGuard<'_, RuntimeProcessState>,
+        launch: RuntimeLaunchConfig,
+        session_path: PathBuf,
+        dry_run: bool,
+    ) -> Result<DesktopRuntimeStatus, String> {
+        let targets = if launch.mode == RuntimeMode::RemoteSlim {
+            format!("web port {} and {}", launch.web_port, launch.backend_url)
+        } else {
+            format!(
+                "web port {}, {} and mongo port {}",
+                launch.web_port, launch.backend_url, launch.mongo_port
+            )
+        };
+        if dry_run {
+            push_runtime_event(
+                &mut guard,
+                "info",
+                "runtime",
+                format!("Dry run: observe-only, would watch {targets} without spawning anything"),
+            );
+            let mut status = snapshot_status(&guard);
+            status.planned_commands = Some(Vec::new());
+            return Ok(status);
+        }
+        stop_all(&mut guard);
+        arm_launch_state(&mut guard, &launch, session_path);
+        guard.auto_restart = false;
+        guard.observe_only = true;
+        guard.mode = launch.mode;
+        guard.web_port = launch.web_port;
+        guard.backend_port = launch.backend_port;
+        guard.mongo_port = launch.mongo_port;
+        guard.backend_url = launch.backend_url.clone();
+        guard.started_at_ms = Some(now_ms());
+        guard.last_error = None;
+        guard.launch_warnings.clear();
+        persist_runtime_session(&guard);
+        push_runtime_event(
+            &mut guard,
+            "info",
+            "runtime",
+            format!("Observe-only: watching {targets}; nothing will be spawned, restarted or stopped"),
+        );
+        drop(guard);
+        run_observe_pass(&self.state, true);
+        let guard = lock_runtime(&self.state);
+        Ok(self.remember_status(snapshot_status(&guard)))
+    }
+
+    pub fn start(
+        &self,
+        req: DesktopRuntimeStartRequest,
+        on_progress: &dyn Fn(&DesktopRuntimeStartProgress),
+        on_ready: &dyn Fn(&DesktopRuntimeReady),
+    ) -> Result<DesktopRuntimeStatus, String> {
+        self.start_exclusive(req, on_progress, on_ready, None)
+    }
+
+    // "Test my config": starts the stack from an inline profile, waits for readiness, probes
+    // it (unless `run_health` is false) and stops it again. Refused while a runtime is up.
+    pub fn trial_start(
+        &self,
+        profile: serde_json::Value,
+        run_health: Option<bool>,
+    ) -> Result<DesktopRuntimeTrialReport, String> {
+        let began = Instant::now();
+        let began_ms = now_ms();
+        let (profile_errors, profile_warnings) = validate_runtime_profile_value(&profile)?;
+        let mut report = DesktopRuntimeTrialReport {
+            profile_errors,
+            profile_warnings,
+            ..Default::default()
+        };
+        if !report.profile_errors.is_empty() {
+            report.error = Some("profile does not match the runtime profile schema".to_string());
+            return Ok(report);
+        }
+        {
+            let mut guard = lock_runtime(&self.state);
+            reconcile_runtime_state(&mut guard);
+            if guard.running || guard.launch_config.is_some() {
+                return Err("runtime is started; stop it before a trial start".to_string());
+            }
+            push_runtime_event(&mut guard, "info", "runtime", "Trial start requested for an unsaved profile");
+        }
+        let ready: Mutex<Option<DesktopRuntimeReady>> = Mutex::new(None);
+        let request = DesktopRuntimeStartRequest {
+            trial_profile: Some(profile),
+            ..Default::default()
+        };
+        let started = self.start(request, &|_| {}, &|payload| {
+            if let Ok(mut slot) = ready.lock() {
+                *slot = Some(payload.clone());
+            }
+        });
+        // Only this call sets trial_profile_path. Without it the start was refused (another start
+        // in flight, or a stack came up in between) or failed before launching anything, and
+        // whatever runs now is not ours to stop.
+        if lock_runtime(&self.state).trial_profile_path.is_none() {
+            return match started {
+                Ok(_) => Err("runtime is started; stop it before a trial start".to_string()),
+                Err(err) => Err(err),
+            };
+        }
+        report.ready = ready.into_inner().unwrap_or(None);
+        match started {
+            Ok(status) => {
+                report.degraded = status.start_phase.as_deref() == Some("degraded");
+                report.started = true;
+                if report.degraded {
+                    report.error = status.last_error;
+                }
+            }
+            Err(err) => report.error = Some(err),
+        }
+        if report.started && run_health.unwrap_or(true) {
+            report.health = Some(self.health());
+        }
+        match self.stop(None) {
+            Ok(_) => report.stopped = true,
+            Err(err) => report.error = Some(report.error.map_or(err.clone(), |first| format!("{first}; stop failed: {err}"))),
+        }
+        let mut guard = lock_runtime(&self.state);
+        if let Some(path) = guard.trial_profile_path.take() {
+            let _ = fs::remove_file(path);
+        }
+        report.ok = report.started
+            && !report.degraded
+            && report.stopped
+            && report.health.as_ref().is_none_or(|health| health.healthy);
+        report.duration_ms = began.elapsed().as_millis() as u64;
+        push_runtime_event_with(
+            &mut guard,
+            if report.ok { "info" } else { "warn" },
+            "runtime",
+            format!("Trial start {} in {}ms", if report.ok { "passed" } else { "failed" }, report.duration_ms),
+            event_fields([("ok", report.ok.into()), ("duration_ms", report.duration_ms.into())]),
+        );
+        report.events = guard.events.iter().filter(|event| event.ts_ms >= began_ms).cloned().collect();
+        Ok(report)
+    }
+
+    // `start` reporting everything on one sink: progress, every diagnostics event pushed
+    // meanwhile, then exactly one terminal ready / degraded / failed / done event.
+    pub fn start_streaming(
+        &self,
+        req: DesktopRuntimeStartRequest,
+        sink: StartEventSink,
+    ) -> Result<DesktopRuntimeStatus, String> {
+        let ready_sent = std::cell::Cell::new(false);
+        let on_progress = |progress: &DesktopRuntimeStartProgress| {
+            let mut message = DesktopRuntimeStartEvent::new("progress");
+            message.progress = Some(progress.clone());
+            sink(&message);
+        };
+        let on_ready = |ready: &DesktopRuntimeReady| {
+            let mut message = DesktopRuntimeStartEvent::new("ready");
+            message.ready = Some(ready.clone());
+            sink(&message);
+            ready_sent.set(true);
+        };
+        let result = self.start_exclusive(req, &on_progress, &on_ready, Some(StartStream(sink.clone())));
+        let terminal = match &result {
+            Err(err) => {
+                let mut message = DesktopRuntimeStartEvent::new("failed");
+                message.error = Some(err.clone());
+                Some(message)
+            }
+            Ok(_) if ready_sent.get() => None,
+            Ok(status) if status.start_phase.as_deref() == Some("degraded") => {
+                let mut message = DesktopRuntimeStartEvent::new("degraded");
+                message.error = status.last_error.clone();
+                Some(message)
+            }
+            // Already running, dry run or observe-only: nothing was brought up.
+            Ok(_) => Some(DesktopRuntimeStartEvent::new("done")),
+        };
+        if let Some(message) = terminal {
+            sink(&message);
+        }
+        result
+    }
+
+    fn start_exclusive(
+        &self,
+        req: DesktopRuntimeStartRequest,
+        on_progress: &dyn Fn(&DesktopRuntimeStartProgress),
+        on_ready: &dyn Fn(&DesktopRuntimeReady),
+        stream: Option<StartStream>,
+    ) -> Result<DesktopRuntimeStatus, String> {
+        if self.starting.swap(true, Ordering::SeqCst) {
+            // The running start holds the lock; the refusal is only logged if it is free by now.
+            if let Ok(mut guard) = self.state.try_lock() {
+                push_runtime_event(&mut guard, "warn", "runtime", "Start requested while another start is in progress");
+            }
+            return Err("already starting".to_string());
+        }
+        let result = match self.state.lock() {
+            Ok(mut guard) => {
+                guard.start_stream = stream;
+                drop(guard);
+                self.start_guarded(req, on_progress, on_ready)
+            }
+            Err(_) => Err("desktop runtime mutex poisoned".to_string()),
+        };
+        lock_runtime(&self.state).start_stream = None;
+        self.starting.store(false, Ordering::SeqCst);
+        result
+    }
+
+    fn start_guarded(
+        &self,
+        req: DesktopRuntimeStartRequest,
+        on_progress: &dyn Fn(&DesktopRuntimeStartProgress),
+        on_ready: &dyn Fn(&DesktopRuntimeReady),
+    ) -> Result<DesktopRuntimeStatus, String> {
+        let started = Instant::now();
+        let mut guard = lock_runtime(&self.state);
+        reconcile_runtime_state(&mut guard);
+        let dry_run = req.dry_run.unwrap_or(false);
+        if guard.running && !dry_run {
+            push_runtime_event(&mut guard, "info", "runtime", "Start requested while already running");
+            return Ok(self.remember_status(snapshot_status(&guard)));
+        }
+
+        let profile_path = req
+            .profile_path
+            .or_else(|| env::var("RUNTIME_PROFILE_PATH").ok())
+            .unwrap_or_default();
+        let trial_profile = req.trial_profile;
+        let mut profile = match trial_profile.clone() {
+            Some(raw) => {
+                serde_json::from_value::<RuntimeProfile>(raw).map_err(|err| format!("invalid trial profile: {err}"))?
+            }
+            None => load_runtime_profile(Some(&profile_path)),
+        };
+        let observe_only = req.observe_only.or(profile.observe_only).unwrap_or(false);
+        if let Some(data_dir) = guard.data_dir_override.clone().or_else(env_data_dir) {
+            profile.data_dir = Some(data_dir);
+        }
+        guard.min_event_level = profile
+            .min_event_level
+            .as_deref()
+            .map(EventLevel::from_raw)
+            .unwrap_or(EventLevel::Trace);
+        guard.log_to_stderr = env_log_to_stderr().or(profile.log_to_stderr).unwrap_or(false);
+        guard.persist_diagnostics = env_persist_diagnostics().or(profile.persist_diagnostics).unwrap_or(true);
+        if !guard.persist_diagnostics {
+            // Events so far stay in memory; the file they came from is left as it is.
+            guard.diagnostics_path = None;
+        }
+        // Set before anything else is logged so every event of this run carries it.
+        let correlation_id = req
+            .correlation_id
+            .clone()
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty());
+        guard.correlation_id = correlation_id.clone();
+        let explicit_session_id = req
+            .session_id
+            .clone()
+            .or_else(|| env::var("DESKTOP_SESSION_ID").ok())
+            .or(profile.session_id.clone())
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty());
+        let desktop_session_id = explicit_session_id
+            .clone()
+            .unwrap_or_else(|| format!("desktop-{}", now_ms()));
+        guard.desktop_session_id = Some(desktop_session_id.clone());
+        guard.instance_label = Some(
+            req.instance_label
+                .clone()
+                .or(profile.instance_label.clone())
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+                .unwrap_or_else(|| desktop_session_id.clone()),
+        );
+        let runtime_scope = runtime_scope_for(explicit_session_id.as_deref(), &profile_path);
+        let runtime_dir = scoped_runtime_dir(profile.data_dir.as_deref(), runtime_scope.as_deref());
+        if let Some(message) = migrate_legacy_runtime_dir(&runtime_dir_for_data_dir(profile.data_dir.as_deref()), &runtime_dir) {
+            push_runtime_event(&mut guard, "info", "runtime", message);
+        }
+        guard.runtime_scope = runtime_scope.clone();
+        let explicit_diagnostics = if guard.persist_diagnostics {
+            explicit_diagnostics_path(profile.diagnostics_path.as_deref())?
+        } else {
+            None
+        };
+        guard.diagnostics_path_explicit = explicit_diagnostics.is_some();
+        match explicit_diagnostics {
+            Some(path) => switch_diagnostics_path(&mut guard, path),
+            None => switch_diagnostics_path(&mut guard, runtime_dir.join("runtime-events.json")),
+        }
+        if let Some(warning) = cwd_data_dir_warning().filter(|_| profile.data_dir.as_deref().is_none_or(|dir| dir.trim().is_empty())) {
+            push_runtime_event(&mut guard, "warn", "runtime", warning);
+        }
+
+        let mode_raw = req
+            .mode
+            .clone()
+            .or_else(|| env::var("APP_RUNTIME_MODE").ok())
+            .or(profile.mode.clone())
+            .unwrap_or_else(|| "local_fullstack".to_string());
+        let mode = RuntimeMode::from_raw(&mode_raw);
+        let ports = profile.local_ports.clone().unwrap_or_default();
+        let web_dev = req.web_dev.unwrap_or(false);
+        // Port 0 means "any free port": one is picked up front so it can be passed to the
+        // sidecar, waited on and reported like a configured one. `next dev` is pinned to 3000.
+        let mut picked_ports: Vec<String> = Vec::new();
+        let mut resolve_port = |service: &str, configured: Option<u16>, default: u16, launched: bool| {
+            match configured.unwrap_or(default) {
+                0 if service == "web" && web_dev => Ok(WEB_DEV_PORT),
+                0 if launched => {
+                    let port = pick_free_port()?;
+                    picked_ports.push(format!("{service}={port}"));
+                    Ok(port)
+                }
+                port => Ok::<u16, String>(port),
+            }
+        };
+        let local = mode == RuntimeMode::LocalFullstack;
+        let web_port = resolve_port("web", ports.web, DEFAULT_WEB_PORT, true)?;
+        let backend_port = resolve_port("backend", ports.backend, DEFAULT_BACKEND_PORT, local)?;
+        let mongo_port = resolve_port("mongo", ports.mongo, DEFAULT_MONGO_PORT, local)?;
+        if !picked_ports.is_empty() {
+            push_runtime_event(
+                &mut guard,
+                "info",
+                "runtime",
+                format!("Picked free ports for port 0: {}", picked_ports.join(", ")),
+            );
+        }
+
+        let workspace_root = match resolve_workspace_root(req.workspace_root.as_deref()) {
+            Ok(root) => root,
+            Err(err) => {
+                push_runtime_event(&mut guard, "error", "preflight", err.clone());
+                guard.last_error = Some(err.clone());
+                return Err(err);
+            }
+        };
+        // Recorded before validation so a failed start can still echo what was resolved.
+        guard.workspace_root = Some(workspace_root.clone());
+        guard.web_dir = Some(workspace_root.join("web"));
+        guard.backend_dir = Some(workspace_root.join("backend"));
+        if mode == RuntimeMode::RemoteSlim && profile.watch_paths.as_ref().is_some_and(|paths| !paths.is_empty()) {
+            push_runtime_event(&mut guard, "info", "watch", "watch_paths ignored in remote_slim mode");
+        }
+        // The sidecars read the profile from a file, so a trial profile gets a scratch copy
+        // that `trial_start` removes again.
+        let sidecar_profile_path = match trial_profile {
+            Some(raw) => {
+                let path = runtime_dir.join("trial-profile.json");
+                let body = serde_json::to_string_pretty(&raw).map_err(|err| format!("invalid trial profile: {err}"))?;
+                fs::create_dir_all(&runtime_dir)
+                    .and_then(|_| fs::write(&path, body))
+                    .map_err(|err| format!("cannot write trial profile {}: {err}", path.display()))?;
+                guard.trial_profile_path = Some(path.clone());
+                path.to_string_lossy().to_string()
+            }
+            None => profile_path.clone(),
+        };
+        let built = RuntimeLaunchConfigBuilder::new(mode, &workspace_root, &runtime_dir)
+            .ports(web_port, backend_port, mongo_port)
+            .session(desktop_session_id.clone(), correlation_id.clone())
+            .runtime_profile_path(&sidecar_profile_path)
+            .web_dev(web_dev)
+            .binaries(
+                req.python_bin.or_else(|| env::var("PYTHON_BIN").ok()),
+                req.mongo_bin.clone().or_else(|| env::var("MONGOD_BIN").ok()),
+            )
+            .profile(&profile)
+            .observe_only(observe_only)
+            .backend_debug(req.backend_debug)
+            .build();
+        let mut launch = match built {
+            Ok(launch) => launch,
+            Err(err) => {
+                push_runtime_event(&mut guard, "error", "preflight", err.clone());
+                guard.last_error = Some(err.clone());
+                return Err(err);
+            }
+        };
+
+        guard.launch_profile = if guard.trial_profile_path.is_some() {
+            None
+        } else {
+            normalize_path(&profile_path).and_then(|path| {
+                let raw = fs::read_to_string(&path).ok()?;
+                Some((path, serde_json::from_str::<serde_json::Value>(&raw).ok()?))
+            })
+        };
+
+        if observe_only {
+            return self.start_observing(guard, launch, runtime_dir.join("runtime-session.json"), dry_run);
+        }
+
+        let (coherence_warnings, coherence_errors) = check_launch_coherence(&launch, &profile);
+        for warning in &coherence_warnings {
+            push_runtime_event(&mut guard, "warn", "preflight", warning.clone());
+        }
+        for error in &coherence_errors {
+            push_runtime_event(&mut guard, "error", "preflight", error.clone());
+        }
+        guard.launch_warnings = coherence_warnings
+            .iter()
+            .cloned()
+            .chain(coherence_errors.iter().map(|error| format!("error: {error}")))
+            .collect();
+
+        if dry_run {
+            let planned = plan_launch_commands(&launch);
+            push_runtime_event(
+                &mut guard,
+                "info",
+                "runtime",
+                format!(
+                    "Dry run: would spawn {}",
+                    planned
+                        .iter()
+                        .map(|command| command.service.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            );
+            let mut status = snapshot_status(&guard);
+            status.planned_commands = Some(planned);
+            return Ok(status);
+        }
+
+        if !coherence_errors.is_empty() {
+            let message = coherence_errors.join(" | ");
+            guard.last_error = Some(message.clone());
+            return Err(message);
+        }
+
+        let version_problems = check_min_versions(&launch, &profile);
+        if !version_problems.is_empty() {
+            let enforce = profile.enforce_min_versions.unwrap_or(false);
+            let level = if enforce { "error" } else { "warn" };
+            for problem in &version_problems {
+                push_runtime_event(&mut guard, level, "preflight", problem.clone());
+            }
+            if enforce {
+                let message = version_problems.join(" | ");
+                guard.last_error = Some(message.clone());
+                return Err(message);
+            }
+        }
+
+        stop_processes(&mut guard);
+        push_runtime_event(
+            &mut guard,
+            "info",
+            "runtime",
+            format!(
+                "Start requested: mode={} web_port={} backend_port={} mongo_port={}",
+                mode.as_str(),
+                web_port,
+                backend_port,
+                mongo_port
+            ),
+        );
+        let env_overrides = describe_env_overrides(&launch);
+        if !env_overrides.is_empty() {
+            push_runtime_event(
+                &mut guard,
+                "info",
+                "runtime",
+                format!("Env overrides: {env_overrides}"),
+            );
+        }
+        let max_attempts = profile.start_max_attempts.unwrap_or(1).clamp(1, START_MAX_ATTEMPTS_CAP);
+        let session_path = runtime_dir.join("runtime-session.json");
+        let partial_start_ok = profile.partial_start_ok.unwrap_or(false);
+        let mut attempt = 1;
+        let unready = loop {
+            arm_launch_state(&mut guard, &launch, session_path.clone());
+            report_start_progress(on_progress, self, &mut guard, "starting", 0.0);
+            let mut attempt_launch = launch.clone();
+            let reason = match launch_services(on_progress, self, &mut guard, &mut attempt_launch, partial_start_ok) {
+                Ok(failed) => {
+                    launch = attempt_launch;
+                    break failed;
+                }
+                Err(reason) => reason,
+            };
+            // stop_all on every failed attempt so no half-started sidecar outlives it.
+            stop_all(&mut guard);
+            if attempt >= max_attempts || shutdown_requested() {
+                let reason = if max_attempts > 1 && attempt >= max_attempts {
+                    format!("start failed after {max_attempts} attempts: {reason}")
+                } else {
+                    reason
+                };
+                push_runtime_event(&mut guard, "error", "runtime", reason.clone());
+                guard.last_error = Some(reason.clone());
+                report_start_progress(on_progress, self, &mut guard, "failed", 0.0);
+                return Err(reason);
+            }
+            push_runtime_event_with(
+                &mut guard,
+                "warn",
+                "runtime",
+                format!(
+                    "Start attempt {attempt}/{max_attempts} failed: {reason}; retrying in {}ms",
+                    START_RETRY_DELAY.as_millis()
+                ),
+                event_fields([
+                    ("attempt", attempt.into()),
+                    ("max_attempts", max_attempts.into()),
+                ]),
+            );
+            std::thread::sleep(START_RETRY_DELAY);
+            attempt += 1;
+        };
+
+        guard.mode = mode;
+        guard.started_at_ms = Some(now_ms());
+        guard.web_port = launch.web_port;
+        guard.backend_port = launch.backend_port;
+        guard.mongo_port = mongo_port;
+        guard.backend_url = launch.backend_url.clone();
+        if unready.is_empty() {
+            guard.running = true;
+            guard.last_error = None;
+            persist_runtime_session(&guard);
+            let ready = DesktopRuntimeReady {
+                ts_ms: now_ms(),
+                mode: mode.as_str().to_string(),
+                web_port: launch.web_port,
+                backend_port: launch.backend_port,
+                mongo_port,
+                backend_url: launch.backend_url.clone(),
+                ready_ms: guard
+                    .ready_durations_ms
+                    .iter()
+                    .map(|(service, ms)| (service.to_string(), *ms))
+                    .collect(),
+                mongo_fresh: is_mongo_required(&launch).then_some(guard.mongo_fresh),
+                startup_ms: started.elapsed().as_millis() as u64,
+            };
+            let mut fields = event_fields([
+                ("mode", ready.mode.as_str().into()),
+                ("web_port", ready.web_port.into()),
+                ("backend_port", ready.backend_port.into()),
+                ("mongo_port", ready.mongo_port.into()),
+                ("startup_ms", ready.startup_ms.into()),
+                (
+                    "ready_ms",
+                    serde_json::Value::Object(
+                        ready
+                            .ready_ms
+                            .iter()
+                            .map(|(service, ms)| (service.clone(), serde_json::Value::from(*ms)))
+                            .collect(),
+                    ),
+                ),
+            ]);
+            if let Some(fresh) = ready.mongo_fresh {
+                fields.insert("mongo_fresh".to_string(), fresh.into());
+            }
+            push_runtime_event_with(
+                &mut guard,
+                "info",
+                "runtime",
+                format!("Runtime started successfully in {}ms", ready.startup_ms),
+                fields,
+            );
+            verify_backend_config(&mut guard, &launch);
+            on_ready(&ready);
+            // A trial stack is stopped right away; the hook would only race that.
+            if guard.trial_profile_path.is_none() {
+                spawn_on_ready_hook(&self.state, &mut guard, "start");
+            }
+        } else {
+            guard.running = recompute_running(&guard);
+            let message = format!("degraded start: {}", unready.join(" | "));
+            guard.last_error = Some(message.clone());
+            persist_runtime_session(&guard);
+            push_runtime_event(&mut guard, "warn", "runtime", message);
+            report_start_progress(on_progress, self, &mut guard, "degraded", 1.0);
+        }
+
+        Ok(self.remember_status(snapshot_status(&guard)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(level: EventLevel, message: &str) -> DesktopRuntimeDiagEvent {
+        DesktopRuntimeDiagEvent {
+            ts_ms: 0,
+            level,
+            source: EventSource::Runtime,
+            message: message.to_string(),
+            session_id: None,
+            correlation_id: None,
+            instance_label: None,
+            fields: serde_json::Map::new(),
+        }
+    }
+
+    fn messages(ring: &EventRing) -> Vec<&str> {
+        ring.iter().map(|event| event.message.as_str()).collect()
+    }
+
+    #[test]
+    fn event_ring_iterates_in_insertion_order_across_classes() {
+        let mut ring = EventRing::default();
+        ring.push(event(EventLevel::Info, "a"));
+        ring.push(event(EventLevel::Warn, "b"));
+        ring.push(event(EventLevel::Debug, "c"));
+        ring.push(event(EventLevel::Error, "d"));
+        ring.push(event(EventLevel::Info, "e"));
+        assert_eq!(messages(&ring), ["a", "b", "c", "d", "e"]);
+        assert_eq!(ring.len(), 5);
+    }
+
+    #[test]
+    fn event_ring_noise_does_not_evict_severe_events() {
+        let mut ring = EventRing::default();
+        ring.push(event(EventLevel::Info, "first"));
+        ring.push(event(EventLevel::Error, "cause"));
+        for index in 0..MAX_EVENTS {
+            ring.push(event(EventLevel::Info, &format!("noise {index}")));
+        }
+        let kept = messages(&ring);
+        assert_eq!(ring.len(), MAX_EVENTS + 1);
+        assert_eq!(kept[0], "cause");
+        assert_eq!(kept[1], "noise 0");
+        assert!(!kept.contains(&"first"));
+    }
+
+    #[test]
+    fn normalize_backend_url_spells_out_scheme_and_port() {
+        for raw in ["127.0.0.1:8080", "http://127.0.0.1:8080/", "HTTP://127.0.0.1:8080", " http://127.0.0.1:8080 "] {
+            assert_eq!(normalize_backend_url(raw).as_deref(), Ok("http://127.0.0.1:8080"), "{raw}");
+        }
+        assert_eq!(normalize_backend_url("http://LocalHost").as_deref(), Ok("http://localhost:80"));
+        assert_eq!(normalize_backend_url("https://example.com/api/").as_deref(), Ok("https://example.com:443/api"));
+        assert_eq!(normalize_backend_url("http://[::1]:8000").as_deref(), Ok("http://[::1]:8000"));
+    }
+
+    #[test]
+    fn normalize_backend_url_rejects_malformed_urls() {
+        for raw in [
+            "http://exa mple.com",
+            "ftp://example.com",
+            "http://127.0.0.1:0",
+            "http://127.0.0.1:99999",
+            "http://127.0.0.1:8080/x?y=1",
+            "http://user@example.com",
+            "http://",
+        ] {
+            assert!(normalize_backend_url(raw).is_err(), "{raw}");
+        }
+    }
+
+    #[test]
+    fn profile_drift_lists_changed_nested_paths() {
+        let running = serde_json::json!({
+            "mode": "local_fullstack",
+            "local_ports": { "web": 3000, "backend": 8080 },
+            "env": { "FOO": "1" },
+        });
+        let current = serde_json::json!({
+            "mode": "local_fullstack",
+            "local_ports": { "web": 3000, "backend": 8081 },
+            "env": { "BAR": "2" },
+            "tasks": [],
+        });
+        let mut drift = Vec::new();
+        profile_drift(&running, &current, "", &mut drift);
+        assert_eq!(drift, ["env.BAR", "env.FOO", "local_ports.backend", "tasks"]);
+
+        let mut none = Vec::new();
+        profile_drift(&running, &running.clone(), "", &mut none);
+        assert!(none.is_empty());
+    }
+
+    #[test]
+    fn event_pattern_masks_digit_runs_and_whitespace() {
+        assert_eq!(event_pattern("backend ready on port 8081 after 1234ms"), "backend ready on port # after #ms");
+        assert_eq!(event_pattern("pid  42\texited (code 1)"), "pid # exited (code #)");
+        assert_eq!(event_pattern("v1.2.3"), "v#.#.#");
+    }
+
+    #[test]
+    fn ready_streak_needs_consecutive_successes() {
+        let mut ready = ReadyStreak::new(3);
+        let seen: Vec<bool> = [true, true, false, true, true, true].into_iter().map(|ok| ready.observe(ok)).collect();
+        assert_eq!(seen, [false, false, false, false, false, true]);
+        assert_eq!(ready.probes, 6);
+        assert_eq!(ready.min_window(), (READY_POLL_INTERVAL + READY_POLL_JITTER) * 3);
+
+        let mut first = ReadyStreak::new(0);
+        assert!(first.observe(true));
+        assert_eq!(first.probes, 1);
+    }
+
+    #[test]
+    fn parse_logged_port_reads_listening_lines_only() {
+        let web = "connecting to mongodb://127.0.0.1:27017\n   - Local:        http://localhost:3001\n";
+        assert_eq!(parse_logged_port(web), Some(3001));
+        let uvicorn = "INFO:     Uvicorn running on http://127.0.0.1:8081 (Press CTRL+C to quit)";
+        assert_eq!(parse_logged_port(uvicorn), Some(8081));
+        let moved = "ready on http://0.0.0.0:3000\nPort 3000 is in use\nready on http://0.0.0.0:3002\n";
+        assert_eq!(parse_logged_port(moved), Some(3002));
+        assert_eq!(parse_logged_port("mongo uri mongodb://127.0.0.1:27017\nLLM at http://127.0.0.1:11434"), None);
+    }
+
+    #[test]
+    fn schema_pattern_matches_its_subset() {
+        let name = SchemaPattern::compile("^[a-z][a-z0-9_-]*$").expect("compiles");
+        assert!(name.is_match("web_1"));
+        assert!(!name.is_match("1web"));
+        assert!(!name.is_match("Web"));
+        let digits = SchemaPattern::compile("\\d{2,3}").expect("compiles");
+        assert!(digits.is_match("port 123"));
+        assert!(!digits.is_match("port 1"));
+        let exact = SchemaPattern::compile("^\\d{2}$").expect("compiles");
+        assert!(!exact.is_match("123"));
+        assert!(SchemaPattern::compile("(a|b)").is_none());
+        assert!(SchemaPattern::compile("a+?").is_none());
+    }
+
+    fn bson_ok(kind: u8, value: &[u8]) -> Vec<u8> {
+        let mut doc = vec![0, 0, 0, 0, 0x08];
+        doc.extend_from_slice(b"ismaster\0");
+        doc.push(1);
+        doc.push(kind);
+        doc.extend_from_slice(b"ok\0");
+        doc.extend_from_slice(value);
+        doc.push(0);
+        let len = doc.len() as i32;
+        doc[..4].copy_from_slice(&len.to_le_bytes());
+        doc
+    }
+
+    #[test]
+    fn mongo_reply_ok_reads_double_and_int32() {
+        assert_eq!(mongo_reply_ok(&bson_ok(0x01, &1.0f64.to_le_bytes())), Some(true));
+        assert_eq!(mongo_reply_ok(&bson_ok(0x01, &0.0f64.to_le_bytes())), Some(false));
+        assert_eq!(mongo_reply_ok(&bson_ok(0x10, &1i32.to_le_bytes())), Some(true));
+        assert_eq!(mongo_reply_ok(&bson_ok(0x01, &[0, 0])), None);
+        assert_eq!(mongo_reply_ok(b"\x05\0\0\0\0"), None);
+    }
+
+    #[test]
+    fn incomplete_utf8_suffix_counts_a_split_character() {
+        assert_eq!(incomplete_utf8_suffix(b"abc"), 0);
+        assert_eq!(incomplete_utf8_suffix("a€".as_bytes()), 0);
+        assert_eq!(incomplete_utf8_suffix(b"a\xE2\x82"), 2);
+        assert_eq!(incomplete_utf8_suffix(b"a\xC3"), 1);
+        assert_eq!(incomplete_utf8_suffix(b"\xF0\x9F\x98"), 3);
+        assert_eq!(incomplete_utf8_suffix(b""), 0);
+    }
+}
@@ -422,6 +422,9 @@ export type DesktopRuntimeStatus = {
   web_pid?: number | null
   backend_pid?: number | null
   mongo_pid?: number | null
+  web_owned?: boolean | null
+  backend_owned?: boolean | null
+  mongo_owned?: boolean | null
   started_at_ms?: number | null
   last_error?: string | null
   web_port: number
@@ -430,8 +433,61 @@ export type DesktopRuntimeStatus = {
   backend_url: string
   auto_restart?: boolean
   restart_count?: number
+  unhealthy_restart_count?: number
   last_restart_ms?: number | null
+  restart_backoff_until_ms?: number | null
+  backend_debug_port?: number | null
+  backend_replicas?: Array<{ service: string; port: number; pid?: number | null; healthy?: boolean | null }>
   diagnostics_path?: string | null
+  planned_commands?: DesktopRuntimeCommandPreview[] | null
+  port_adjusted?: boolean
+  backend_scheme?: "http" | "https" | string
+  mongo_dbpath?: string | null
+  mongo_fresh?: boolean
+  launch_warnings?: string[]
+  desktop_session_id?: string | null
+  correlation_id?: string | null
+  instance_label?: string | null
+  start_phase?: string | null
+  start_progress?: number
+  observe_only?: boolean
+  watchdog_suspended?: boolean
+  restart_in_progress?: boolean
+  restarting_services?: string[]
+  config_drift?: boolean
+  config_drift_fields?: string[]
+  last_stop?: Array<{ service: string; was_running: boolean; graceful: boolean; forced: boolean; exit_observed: boolean; stop_ms: number }>
+  observed?: DesktopRuntimeProbe[]
+  backend_health?: unknown
+  workspace_root?: string | null
+  web_dir?: string | null
+  backend_dir?: string | null
+}
+
+export type DesktopRuntimeCommandPreview = {
+  service: string
+  program: string
+  args: string[]
+  cwd?: string | null
+  env: Record<string, string | null>
+}
+
+export type DesktopRuntimeStartProgress = {
+  phase: string
+  progress: number
+  ts_ms: number
+}
+
+export type DesktopRuntimeReady = {
+  ts_ms: number
+  mode: "local_fullstack" | "remote_slim" | string
+  web_port: number
+  backend_port: number
+  mongo_port: number
+  backend_url: string
+  ready_ms: Record<string, number>
+  mongo_fresh?: boolean | null
+  startup_ms: number
 }
 
 export type DesktopRuntimeDiagEvent = {
@@ -439,12 +495,107 @@ export type DesktopRuntimeDiagEvent = {
   level: string
   source: string
   message: string
+  session_id?: string | null
+  correlation_id?: string | null
+  instance_label?: string | null
+  fields?: Record<string, unknown>
+}
+
+export type DesktopRuntimeStartEvent = {
+  kind: "progress" | "log" | "ready" | "degraded" | "failed" | "done" | string
+  ts_ms: number
+  progress?: DesktopRuntimeStartProgress
+  event?: DesktopRuntimeDiagEvent
+  ready?: DesktopRuntimeReady
+  error?: string
 }
 
 export type DesktopRuntimeDiagnostics = {
   generated_at_ms: number
   status: DesktopRuntimeStatus
   events: DesktopRuntimeDiagEvent[]
+  matched: number
+}
+
+export type DesktopRuntimeLastError = {
+  error?: string | null
+  ts_ms?: number | null
+  context: DesktopRuntimeDiagEvent[]
+}
+
+export type DesktopRuntimeTrialReport = {
+  ok: boolean
+  profile_errors: Array<{ pointer: string; message: string }>
+  profile_warnings: Array<{ pointer: string; message: string }>
+  started: boolean
+  degraded: boolean
+  ready?: DesktopRuntimeReady | null
+  health?: { checked_at_ms: number; mode: string; healthy: boolean; probes: DesktopRuntimeProbe[] } | null
+  stopped: boolean
+  error?: string | null
+  duration_ms: number
+  events: DesktopRuntimeDiagEvent[]
+}
+
+export type DesktopRuntimePaths = {
+  started: boolean
+  data_dir?: string | null
+  runtime_dir: string
+  diagnostics_path: string
+  log_dir: string
+  session_path: string
+  mongo_dbpath?: string | null
+}
+
+export type DesktopRuntimeCommandLine = {
+  service: string
+  program: string
+  args: string[]
+  cwd?: string | null
+  env: Record<string, string | null>
+}
+
+export type DesktopRuntimeProbe = {
+  target: string
+  ok: boolean
+  latency_ms: number
+  message: string
+  kind?: "mongo" | "http" | "tcp_tls" | string
+  address?: string | null
+  http_status?: number | null
+  error?: string | null
+}
+
+export type DesktopRuntimeDiffEntry = {
+  source: string
+  level: string
+  pattern: string
+  a_count: number
+  b_count: number
+  a_offset_ms?: number | null
+  b_offset_ms?: number | null
+  a_example?: string | null
+  b_example?: string | null
+}
+
+export type DesktopRuntimeDiagnosticsDiff = {
+  a_path: string
+  b_path: string
+  a_events: number
+  b_events: number
+  a_span_ms: number
+  b_span_ms: number
+  added: DesktopRuntimeDiffEntry[]
+  removed: DesktopRuntimeDiffEntry[]
+  changed: DesktopRuntimeDiffEntry[]
+  timeline: DesktopRuntimeDiffEntry[]
+  truncated: boolean
+}
+
+export type DesktopRuntimeWebEnv = {
+  mode: "local_fullstack" | "remote_slim" | string
+  source: "running" | "profile" | string
+  env: Record<string, string>
 }
 
 export type TokenKind = "plain" | "keyword" | "string" | "number" | "comment" | "operator" | "type" | "builtin"